@@ -2,12 +2,39 @@
 set -e
 
 wit EXTRACT "$1" ./p2filesystem
-mkdir -p ./assets
-mkdir -p ./assets/resulttex
-mkdir -p ./assets/enemytex
-cp -r ./p2filesystem/P-GPVE/files/user/Mukki/mapunits/* ./assets
-cp -r ./p2filesystem/P-GPVE/files/user/Matoba/resulttex/* ./assets/resulttex
-cp ./p2filesystem/P-GPVE/files/user/Yamashita/enemytex/arc.szs ./assets/enemytex
-find ./assets -iname "*.szs" -execdir wszst EXTRACT {} \;
-find ./assets -iname "*.bti" -execdir wimgt DECODE {} \;
+
+# The extracted folder is named after the game ID: P-GPVE (US), P-GPVP (PAL) or P-GPVJ (JP).
+GAME_DIR=$(find ./p2filesystem -mindepth 1 -maxdepth 1 -type d -name "P-GPV*" | head -n 1)
+if [ -z "$GAME_DIR" ]; then
+    GAME_DIR=./p2filesystem
+fi
+
+REGION="$2"
+if [ -z "$REGION" ]; then
+    case "$(basename "$GAME_DIR")" in
+        P-GPVP) REGION=pal ;;
+        P-GPVJ) REGION=jp ;;
+        *) REGION=us ;;
+    esac
+fi
+
+if [ "$REGION" = "us" ]; then
+    ASSETS=./assets
+else
+    ASSETS=./assets/$REGION
+fi
+echo "Extracting $REGION assets into $ASSETS"
+
+# Decode in a separate folder so other regions already in ./assets aren't touched.
+STAGING=./p2assets
+mkdir -p "$STAGING/resulttex"
+mkdir -p "$STAGING/enemytex"
+cp -r "$GAME_DIR"/files/user/Mukki/mapunits/* "$STAGING"
+cp -r "$GAME_DIR"/files/user/Matoba/resulttex/* "$STAGING/resulttex"
+cp "$GAME_DIR"/files/user/Yamashita/enemytex/arc.szs "$STAGING/enemytex"
+find "$STAGING" -iname "*.szs" -execdir wszst EXTRACT {} \;
+find "$STAGING" -iname "*.bti" -execdir wimgt DECODE {} \;
+mkdir -p "$ASSETS"
+cp -r "$STAGING"/* "$ASSETS"
+rm -rf "$STAGING"
 rm -rf ./p2filesystem
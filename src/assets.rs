@@ -1,7 +1,9 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, fmt::{Display, Formatter}, path::PathBuf, str::FromStr};
 use encoding_rs::SHIFT_JIS;
-use rust_embed::RustEmbed;
-use cached::proc_macro::once;
+use rust_embed::{EmbeddedFile, RustEmbed};
+use cached::proc_macro::{cached, once};
+use itertools::Itertools;
+use crate::caveinfo::CaveInfoError;
 
 
 #[derive(RustEmbed)]
@@ -9,22 +11,176 @@ use cached::proc_macro::once;
 #[prefix="assets/"]
 struct Assets;
 
+/// Paths always start with "assets/" and are looked up in the given region's folder
+/// first.
 #[allow(non_snake_case)]
-pub fn get_file_JIS(path: &str) -> Option<String> {
-    let file = Assets::get(path)?;
+pub fn get_file_JIS(path: &str, region: Region) -> Option<String> {
+    let file = get_regional_file(path, region)?;
     Some(SHIFT_JIS.decode(&file.data).0.into_owned())
 }
 
-pub fn get_file_bytes(path: &str) -> Option<Cow<'static, [u8]>> {
-    let file = Assets::get(path)?;
+pub fn get_file_bytes(path: &str, region: Region) -> Option<Cow<'static, [u8]>> {
+    let file = get_regional_file(path, region)?;
     Some(file.data)
 }
 
 
+/// Which release of the game the assets come from. US assets are extracted straight
+/// into `assets/`, and other regions into their own folder, e.g. `assets/pal/`. Files
+/// missing from a region's folder fall back to the US ones, so files that are the same
+/// in every region don't need to be extracted twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Region {
+    /// NTSC-U, game ID GPVE01.
+    #[default]
+    Us,
+    /// PAL, game ID GPVP01.
+    Pal,
+    /// NTSC-J, game ID GPVJ01.
+    Jp,
+}
+
+impl Region {
+    pub const ALL: [Region; 3] = [Region::Us, Region::Pal, Region::Jp];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Region::Us => "us",
+            Region::Pal => "pal",
+            Region::Jp => "jp",
+        }
+    }
+
+    pub fn game_id(&self) -> &'static str {
+        match self {
+            Region::Us => "GPVE01",
+            Region::Pal => "GPVP01",
+            Region::Jp => "GPVJ01",
+        }
+    }
+
+    /// Where this region's files are in the assets folder.
+    pub fn asset_prefix(&self) -> &'static str {
+        match self {
+            Region::Us => "assets/",
+            Region::Pal => "assets/pal/",
+            Region::Jp => "assets/jp/",
+        }
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+    fn from_str(region: &str) -> Result<Region, String> {
+        match region.to_ascii_lowercase().as_str() {
+            "us" | "usa" | "ntsc-u" | "gpve01" => Ok(Region::Us),
+            "pal" | "eu" | "europe" | "gpvp01" => Ok(Region::Pal),
+            "jp" | "jpn" | "japan" | "ntsc-j" | "gpvj01" => Ok(Region::Jp),
+            _ => Err(format!("Unknown region '{}'. Expected us, pal, or jp", region)),
+        }
+    }
+}
+
+fn get_regional_file(path: &str, region: Region) -> Option<EmbeddedFile> {
+    if region != Region::Us {
+        if let Some(file) = path.strip_prefix("assets/").and_then(|rest| Assets::get(&format!("{}{}", region.asset_prefix(), rest))) {
+            return Some(file);
+        }
+    }
+    Assets::get(path)
+}
+
+/// The folder in the region's `resulttex` folder with its treasure textures. The US
+/// release's folder is "us". Other regions' folder names haven't been checked, so
+/// their folder is whichever one was extracted for them, and it's an error if there
+/// isn't exactly one.
+#[cached(result = true)]
+pub fn treasure_texture_folder(region: Region) -> Result<String, CaveInfoError> {
+    let resulttex_prefix = format!("{}resulttex/", region.asset_prefix());
+    let folders: Vec<String> = Assets::iter()
+        .filter_map(|path| Some(path.strip_prefix(&resulttex_prefix)?.split_once("/arc.d/")?.0.to_string()))
+        .filter(|folder| !folder.contains('/'))
+        .unique()
+        .collect();
+    match (region, folders.as_slice()) {
+        (Region::Us, _) if folders.iter().any(|folder| folder == "us") => Ok("us".to_string()),
+        (Region::Us, _) => Err(CaveInfoError::MissingFileError(format!("{}us", resulttex_prefix))),
+        (_, [folder]) => Ok(folder.clone()),
+        (_, []) => Err(CaveInfoError::MissingFileError(resulttex_prefix)),
+        (_, folders) => Err(CaveInfoError::FileReadError(
+            resulttex_prefix,
+            format!("expected one treasure texture folder for {}, found {}", region, folders.join(", ")),
+        )),
+    }
+}
+
+/// Where to read game files from: the assets embedded at build time, or a folder laid
+/// out the same way as `assets/`, e.g. files extracted from a modded ISO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetSource {
+    /// Assets embedded at build time, read from this region's folder with the US files
+    /// as a fallback.
+    Embedded(Region),
+    Directory(PathBuf),
+}
+
+impl Default for AssetSource {
+    fn default() -> AssetSource {
+        AssetSource::Embedded(Region::Us)
+    }
+}
+
+impl AssetSource {
+    /// Reads a Shift-JIS text file. `path` starts with "assets/" in both cases, e.g.
+    /// "assets/units/tsuchi_conc_units.txt".
+    #[allow(non_snake_case)]
+    pub fn get_file_JIS(&self, path: &str) -> Result<String, CaveInfoError> {
+        match self {
+            AssetSource::Embedded(region) => get_file_JIS(path, *region).ok_or_else(|| CaveInfoError::MissingFileError(path.to_string())),
+            AssetSource::Directory(dir) => {
+                let bytes = std::fs::read(dir.join(path.strip_prefix("assets/").unwrap_or(path))).map_err(|e| match e.kind() {
+                    std::io::ErrorKind::NotFound => CaveInfoError::MissingFileError(path.to_string()),
+                    _ => CaveInfoError::FileReadError(path.to_string(), e.to_string()),
+                })?;
+                Ok(SHIFT_JIS.decode(&bytes).0.into_owned())
+            },
+        }
+    }
+
+    /// Whether the folder exists and has anything in it, e.g. "assets/arc/room_4x4a_4_conc".
+    pub fn has_folder(&self, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        match self {
+            AssetSource::Embedded(region) => {
+                let regional_path = format!("{}{}/", region.asset_prefix(), path.strip_prefix("assets/").unwrap_or(path));
+                Assets::iter().any(|file| file.starts_with(&regional_path) || file.starts_with(&format!("{}/", path)))
+            },
+            AssetSource::Directory(dir) => dir.join(path.strip_prefix("assets/").unwrap_or(path)).is_dir(),
+        }
+    }
+}
+
+
 #[once]
 pub fn get_enemy_list() -> Vec<String> {
     Assets::iter()
         .filter_map(|path| path.strip_prefix("assets/enemytex/arc.d/").map(|p| p.to_string()))
         .filter(|path| !path.contains("/"))
         .collect()
-}
\ No newline at end of file
+}
+
+/// File names of every Cave Unit Definition file in `assets/units`.
+#[once]
+pub fn get_unit_definition_list() -> Vec<String> {
+    Assets::iter()
+        .filter_map(|path| path.strip_prefix("assets/units/").map(|p| p.to_string()))
+        .filter(|path| !path.contains("/"))
+        .sorted()
+        .collect()
+}
@@ -0,0 +1,152 @@
+/// Modified copies of existing floors for "what if" experiments, e.g. what SH6 would
+/// look like with 3 treasures, without writing any files.
+
+use super::{expand_rotations, sort_cave_units, CapInfo, CaveInfoError, CaveUnit, FloorInfo, GateInfo, ItemInfo, TekiInfo};
+
+/// Starts from a copy of an existing floor. Units are added and removed from
+/// `unit_definitions`, and `build` sorts and rotates them into `cave_units` the same way
+/// loading does, so the result can go straight into `Layout::generate`.
+/// Changes that can't be made, like removing a gate that isn't there, return errors.
+/// ```ignore
+/// let floor = FloorInfoBuilder::new(SH6.as_ref()?).max_treasures(3).remove_teki("Kochappy")?.build();
+/// let layout = Layout::generate(0x1234ABCD, &floor);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FloorInfoBuilder {
+    floor: FloorInfo,
+}
+
+impl FloorInfoBuilder {
+    pub fn new(base: &FloorInfo) -> FloorInfoBuilder {
+        FloorInfoBuilder { floor: base.clone() }
+    }
+
+    pub fn max_main_objects(mut self, max_main_objects: u32) -> Self {
+        self.floor.max_main_objects = max_main_objects;
+        self
+    }
+
+    pub fn max_treasures(mut self, max_treasures: u32) -> Self {
+        self.floor.max_treasures = max_treasures;
+        self
+    }
+
+    pub fn max_gates(mut self, max_gates: u32) -> Self {
+        self.floor.max_gates = max_gates;
+        self
+    }
+
+    pub fn num_rooms(mut self, num_rooms: u32) -> Self {
+        self.floor.num_rooms = num_rooms;
+        self
+    }
+
+    pub fn corridor_probability(mut self, corridor_probability: f32) -> Self {
+        self.floor.corridor_probability = corridor_probability;
+        self
+    }
+
+    /// In range [0-1], not a percentage.
+    pub fn cap_probability(mut self, cap_probability: f32) -> Self {
+        self.floor.cap_probability = cap_probability;
+        self
+    }
+
+    pub fn has_geyser(mut self, has_geyser: bool) -> Self {
+        self.floor.has_geyser = has_geyser;
+        self
+    }
+
+    pub fn exit_plugged(mut self, exit_plugged: bool) -> Self {
+        self.floor.exit_plugged = exit_plugged;
+        self
+    }
+
+    pub fn is_final_floor(mut self, is_final_floor: bool) -> Self {
+        self.floor.is_final_floor = is_final_floor;
+        self
+    }
+
+    pub fn add_teki(mut self, teki: TekiInfo) -> Self {
+        self.floor.teki_info.push(teki);
+        self
+    }
+
+    /// Removes every TekiInfo entry for this teki, ignoring case.
+    pub fn remove_teki(mut self, internal_name: &str) -> Result<Self, CaveInfoError> {
+        let before = self.floor.teki_info.len();
+        self.floor.teki_info.retain(|teki| !teki.internal_name.eq_ignore_ascii_case(internal_name));
+        let after = self.floor.teki_info.len();
+        self.removed("teki", internal_name, before, after)
+    }
+
+    pub fn add_item(mut self, item: ItemInfo) -> Self {
+        self.floor.item_info.push(item);
+        self
+    }
+
+    /// Removes every ItemInfo entry for this treasure, ignoring case.
+    pub fn remove_item(mut self, internal_name: &str) -> Result<Self, CaveInfoError> {
+        let before = self.floor.item_info.len();
+        self.floor.item_info.retain(|item| !item.internal_name.eq_ignore_ascii_case(internal_name));
+        let after = self.floor.item_info.len();
+        self.removed("treasure", internal_name, before, after)
+    }
+
+    pub fn add_gate(mut self, gate: GateInfo) -> Self {
+        self.floor.gate_info.push(gate);
+        self
+    }
+
+    /// Gates don't have names, so they're removed by their position in GateInfo.
+    pub fn remove_gate(mut self, index: usize) -> Result<Self, CaveInfoError> {
+        if index >= self.floor.gate_info.len() {
+            return Err(CaveInfoError::EditError(format!("can't remove gate {}, the floor only has {} gates", index, self.floor.gate_info.len())));
+        }
+        self.floor.gate_info.remove(index);
+        Ok(self)
+    }
+
+    pub fn add_cap(mut self, cap: CapInfo) -> Self {
+        self.floor.cap_info.push(cap);
+        self
+    }
+
+    /// Removes every CapInfo entry for this teki or treasure, ignoring case.
+    pub fn remove_cap(mut self, internal_name: &str) -> Result<Self, CaveInfoError> {
+        let before = self.floor.cap_info.len();
+        self.floor.cap_info.retain(|cap| !cap.internal_name.eq_ignore_ascii_case(internal_name));
+        let after = self.floor.cap_info.len();
+        self.removed("cap teki", internal_name, before, after)
+    }
+
+    /// Adds a map unit. It has to be unrotated, e.g. one from `load_cave_units` or
+    /// another floor's `unit_definitions`.
+    pub fn add_unit(mut self, unit: CaveUnit) -> Result<Self, CaveInfoError> {
+        if unit.rotation != 0 {
+            return Err(CaveInfoError::EditError(format!("only unrotated units can be added to a floor, and {} is rotated", unit.unit_folder_name)));
+        }
+        self.floor.unit_definitions.push(unit);
+        Ok(self)
+    }
+
+    pub fn remove_unit(mut self, unit_folder_name: &str) -> Result<Self, CaveInfoError> {
+        let before = self.floor.unit_definitions.len();
+        self.floor.unit_definitions.retain(|unit| unit.unit_folder_name != unit_folder_name);
+        let after = self.floor.unit_definitions.len();
+        self.removed("map unit", unit_folder_name, before, after)
+    }
+
+    /// Errors if a `remove_` call didn't find anything to remove.
+    fn removed(self, kind: &str, name: &str, before: usize, after: usize) -> Result<Self, CaveInfoError> {
+        if before == after {
+            return Err(CaveInfoError::EditError(format!("can't remove {} '{}', it isn't on this floor", kind, name)));
+        }
+        Ok(self)
+    }
+
+    pub fn build(mut self) -> FloorInfo {
+        self.floor.cave_units = expand_rotations(sort_cave_units(self.floor.unit_definitions.clone()));
+        self.floor
+    }
+}
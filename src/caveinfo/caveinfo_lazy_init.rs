@@ -1,37 +1,17 @@
 /// Thread safe, write-once, lazy initialized smart pointers for the parsed FloorInfo
 /// objects for every sublevel in Pikmin 2. Caveinfo for each sublevel is exposed as a static
-/// variable, e.g. `SH3`, that can be imported from the `crate::caveinfo` module.
+/// variable, e.g. `SH3`, that can be imported from the `crate::caveinfo` module. Each one
+/// holds an error instead if its cave's files couldn't be loaded.
 
-use std::convert::TryFrom;
 use std::collections::HashMap;
 use once_cell::sync::Lazy;
 use paste::paste;
 use maplit::hashmap;
-use crate::caveinfo::{
-    CaveInfo, CaveInfoError, FloorInfo,
-    cave_name_to_caveinfo_filename,
-    parse::parse_caveinfo,
-};
-use crate::assets::get_file_JIS;
-
-fn load_caveinfo(cave: &'static str) -> Result<CaveInfo, CaveInfoError> {
-    let caveinfo_filename = format!("assets/caveinfo/{}", cave_name_to_caveinfo_filename(&cave));
-    let caveinfo_txt = get_file_JIS(&caveinfo_filename)
-        .ok_or_else(|| CaveInfoError::MissingFileError(caveinfo_filename.clone()))?;
-    let floor_chunks = parse_caveinfo(&caveinfo_txt)
-        .map_err(|_| CaveInfoError::ParseFileError(caveinfo_filename.clone()))?
-        .1;
-
-    let mut result = CaveInfo::try_from(floor_chunks)?;
-    for mut sublevel in result.floors.iter_mut() {
-        sublevel.cave_name = Some(cave.to_owned());
-    }
-    Ok(result)
-}
+use crate::caveinfo::{AssetSource, Region, CaveInfo, CaveInfoError, FloorInfo, canonical_cave_name};
 
 macro_rules! preload_caveinfo {
     ($($rest_cave:ident, $($rest_floors:literal),+),+) => {
-        pub static ALL_SUBLEVELS: [&Lazy<FloorInfo>; 104] = [
+        pub static ALL_SUBLEVELS: [&Lazy<Result<FloorInfo, CaveInfoError>>; 104] = [
             $(
                 $(
                     paste! {
@@ -40,7 +20,7 @@ macro_rules! preload_caveinfo {
                 ),+
             ),+
         ];
-        pub static ALL_SUBLEVELS_MAP: Lazy<HashMap<String, &Lazy<FloorInfo>>> = Lazy::new(|| hashmap! {
+        pub static ALL_SUBLEVELS_MAP: Lazy<HashMap<String, &Lazy<Result<FloorInfo, CaveInfoError>>>> = Lazy::new(|| hashmap! {
             $(
                 $(
                     concat!(stringify!($rest_cave), stringify!($rest_floors)).to_ascii_lowercase() => paste! {&[<$rest_cave $rest_floors>]}
@@ -48,17 +28,31 @@ macro_rules! preload_caveinfo {
             ),+
         });
         preload_caveinfo_individual!($($rest_cave, $($rest_floors),+),+);
+
+        /// The vanilla caveinfo for a cave, loaded on first use.
+        pub fn vanilla_caveinfo(cave: &str) -> Result<&'static CaveInfo, CaveInfoError> {
+            match canonical_cave_name(cave) {
+                $(
+                    Some(stringify!($rest_cave)) => $rest_cave.as_ref().map_err(Clone::clone),
+                )+
+                _ => Err(CaveInfoError::InvalidCave(cave.to_string())),
+            }
+        }
     }
 }
 
 macro_rules! preload_caveinfo_individual {
     ($cave:ident, $($floor:literal),+) => {
         #[allow(non_upper_case_globals)]
-        static $cave: Lazy<CaveInfo> = Lazy::new(|| load_caveinfo(stringify!($cave)).expect(concat!("Failed to load Caveinfo for ", stringify!($cave))));
+        static $cave: Lazy<Result<CaveInfo, CaveInfoError>> = Lazy::new(|| CaveInfo::load(&AssetSource::Embedded(Region::Us), stringify!($cave)));
         $(
             paste! {
                 #[allow(non_upper_case_globals)]
-                pub static [<$cave $floor>]: Lazy<FloorInfo> = Lazy::new(|| $cave.floors[$floor-1].clone());
+                pub static [<$cave $floor>]: Lazy<Result<FloorInfo, CaveInfoError>> = Lazy::new(|| {
+                    let caveinfo = $cave.as_ref().map_err(Clone::clone)?;
+                    caveinfo.floors.get($floor - 1).cloned()
+                        .ok_or_else(|| CaveInfoError::InvalidSublevel(concat!(stringify!($cave), $floor).to_string()))
+                });
             }
         )+
     };
@@ -85,8 +79,10 @@ preload_caveinfo!(
     HoH, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15
 );
 
-pub fn force_load_all() {
+/// Loads every sublevel up front. Returns the first error if any cave couldn't be loaded.
+pub fn force_load_all() -> Result<(), CaveInfoError> {
     for sublevel in ALL_SUBLEVELS {
-        Lazy::force(sublevel);
+        Lazy::force(sublevel).as_ref().map_err(Clone::clone)?;
     }
+    Ok(())
 }
@@ -1,27 +1,50 @@
 use std::{
     error::Error,
-    fmt::{Debug, Display, Formatter},
+    fmt::{Display, Formatter},
     num::{ParseFloatError, ParseIntError},
 };
 
 #[derive(Debug, Clone)]
 pub enum CaveInfoError {
     InvalidSublevel(String),
-    MalformedLine,
+    InvalidCave(String),
     ParseValueError,
-    NoSuchTag(String),
-    MalformedTagLine(String),
-    FileReadError(String),
+    /// The file exists but couldn't be read, and why.
+    FileReadError(String, String),
     MissingFileError(String),
-    ParseFileError(String),
+    ParseError(ParseError),
+    ConversionError(String),
+    /// Something in the caveinfo can't be written in the game's file format.
+    WriteError(String),
+    /// A `FloorInfoBuilder` change that can't be made to the floor.
+    EditError(String),
+}
+
+impl CaveInfoError {
+    /// Attaches a file name to a parse error that doesn't have one yet.
+    pub(super) fn in_file(mut self, file: &str) -> CaveInfoError {
+        if let CaveInfoError::ParseError(ParseError { file: file_name @ None, .. }) = &mut self {
+            *file_name = Some(file.to_string());
+        }
+        self
+    }
 }
 
 impl Error for CaveInfoError {}
 
 impl Display for CaveInfoError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        // Doesn't need to be pretty
-        Debug::fmt(self, f)
+        match self {
+            CaveInfoError::InvalidSublevel(sublevel) => write!(f, "error: invalid sublevel '{}'", sublevel),
+            CaveInfoError::InvalidCave(cave) => write!(f, "error: unknown cave '{}'", cave),
+            CaveInfoError::ParseValueError => write!(f, "error: couldn't parse value"),
+            CaveInfoError::FileReadError(file, reason) => write!(f, "error: couldn't read file '{}': {}", file, reason),
+            CaveInfoError::MissingFileError(file) => write!(f, "error: file '{}' not found", file),
+            CaveInfoError::ParseError(parse_error) => Display::fmt(parse_error, f),
+            CaveInfoError::ConversionError(message) => write!(f, "error: couldn't convert caveinfo to or from JSON or TOML: {}", message),
+            CaveInfoError::WriteError(message) => write!(f, "error: couldn't write caveinfo: {}", message),
+            CaveInfoError::EditError(message) => write!(f, "error: couldn't edit floor: {}", message),
+        }
     }
 }
 
@@ -36,3 +59,80 @@ impl From<ParseFloatError> for CaveInfoError {
         CaveInfoError::ParseValueError
     }
 }
+
+impl From<ParseError> for CaveInfoError {
+    fn from(parse_error: ParseError) -> CaveInfoError {
+        CaveInfoError::ParseError(parse_error)
+    }
+}
+
+
+/// The location of a problem in a CaveInfo, Cave Unit Definition or layout file, and
+/// what was expected there. Displays like a compiler error:
+/// ```text
+/// error: expected a whole number for {f002}, found 'x'
+///   --> assets/caveinfo/tutorial_1.txt:12:11
+///    |
+/// 12 |  {f002} 4 x  # Max main objects
+///    |           ^
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Path of the file in the assets folder, if known.
+    pub file: Option<String>,
+    /// 1-indexed line number.
+    pub line: usize,
+    /// 1-indexed column, counted in characters.
+    pub column: usize,
+    /// The full line the error is on, without the line ending.
+    pub line_text: String,
+    /// The offending text. Empty if something was missing at the end of a line.
+    pub text: String,
+    pub expected: String,
+}
+
+impl ParseError {
+    /// Makes an error pointing at `at`, which has to be a slice of `source`. The error
+    /// covers `at` up to the end of its first line.
+    pub(super) fn new(source: &str, at: &str, expected: impl Into<String>) -> ParseError {
+        let source_start = source.as_ptr() as usize;
+        let offset = (at.as_ptr() as usize)
+            .checked_sub(source_start)
+            .filter(|offset| *offset <= source.len() && source.is_char_boundary(*offset))
+            .unwrap_or(source.len());
+
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line_text = source[line_start..].lines().next().unwrap_or("");
+        ParseError {
+            file: None,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            line_text: line_text.trim_end_matches('\r').to_string(),
+            text: at.lines().next().unwrap_or("").trim_end_matches('\r').to_string(),
+            expected: expected.into(),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.text.is_empty() {
+            writeln!(f, "error: expected {}, found end of line", self.expected)?;
+        } else {
+            writeln!(f, "error: expected {}, found '{}'", self.expected, self.text)?;
+        }
+
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        writeln!(f, "{}--> {}:{}:{}", gutter, self.file.as_deref().unwrap_or("<unknown file>"), self.line, self.column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", line_number, self.line_text.replace('\t', " "))?;
+
+        let indent = " ".repeat(self.column - 1);
+        let underline = "^".repeat(self.text.chars().count().max(1));
+        write!(f, "{} | {}{}", gutter, indent, underline)
+    }
+}
@@ -0,0 +1,244 @@
+/// Differences between two versions of the same cave's caveinfo, e.g. vanilla and a
+/// romhack, or two regions of the game. Floors are compared in order. Within a floor,
+/// teki, treasures and cap teki are matched up by internal name and what they carry,
+/// gates by position, and map units by folder name.
+
+use std::fmt::{Display, Formatter};
+use serde::Serialize;
+
+use super::{CapInfo, CaveInfo, CaveUnit, FloorInfo, GateInfo, ItemInfo, TekiInfo};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaveInfoDiff {
+    pub num_floors: Option<Change<u32>>,
+    /// Only floors that exist in both and have differences.
+    pub floors: Vec<FloorInfoDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FloorInfoDiff {
+    /// 1-indexed, unlike `FloorInfo::sublevel`.
+    pub floor: u32,
+    pub parameters: Vec<ParameterChange>,
+    pub teki: ListDiff<TekiInfo>,
+    pub items: ListDiff<ItemInfo>,
+    pub gates: ListDiff<GateInfo>,
+    pub caps: ListDiff<CapInfo>,
+    pub units: ListDiff<CaveUnit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ListDiff<T> {
+    pub added: Vec<T>,
+    pub removed: Vec<T>,
+    pub changed: Vec<Change<T>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// A changed FloorInfo value. Values are shown as text since they have different types;
+/// missing optional values are `None`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParameterChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl CaveInfo {
+    /// Compares this caveinfo (old) to another (new).
+    pub fn diff(&self, other: &CaveInfo) -> CaveInfoDiff {
+        CaveInfoDiff {
+            num_floors: (self.num_floors != other.num_floors).then(|| Change { old: self.num_floors, new: other.num_floors }),
+            floors: self.floors.iter().zip(other.floors.iter())
+                .map(|(old, new)| old.diff(new))
+                .filter(|floor_diff| !floor_diff.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl FloorInfo {
+    /// Compares this floor (old) to another (new).
+    pub fn diff(&self, other: &FloorInfo) -> FloorInfoDiff {
+        FloorInfoDiff {
+            floor: self.sublevel + 1,
+            parameters: parameter_changes(self, other),
+            teki: diff_lists(&self.teki_info, &other.teki_info, |teki| (teki.internal_name.clone(), teki.carrying.clone()), PartialEq::eq),
+            items: diff_lists(&self.item_info, &other.item_info, |item| item.internal_name.clone(), PartialEq::eq),
+            // Gates don't have names, so the nth gate of each floor is compared.
+            gates: diff_by_position(&self.gate_info, &other.gate_info),
+            caps: diff_lists(&self.cap_info, &other.cap_info, |cap| (cap.internal_name.clone(), cap.carrying.clone()), PartialEq::eq),
+            units: diff_lists(&self.unit_definitions, &other.unit_definitions, |unit| unit.unit_folder_name.clone(), same_unit),
+        }
+    }
+}
+
+impl CaveInfoDiff {
+    pub fn is_empty(&self) -> bool {
+        self.num_floors.is_none() && self.floors.is_empty()
+    }
+}
+
+impl FloorInfoDiff {
+    pub fn is_empty(&self) -> bool {
+        self.parameters.is_empty() && self.teki.is_empty() && self.items.is_empty()
+            && self.gates.is_empty() && self.caps.is_empty() && self.units.is_empty()
+    }
+}
+
+impl<T> ListDiff<T> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Entries with the same key are paired up in order. Leftovers on either side are
+/// added or removed.
+fn diff_lists<T: Clone, K: PartialEq>(old: &[T], new: &[T], key: impl Fn(&T) -> K, same: impl Fn(&T, &T) -> bool) -> ListDiff<T> {
+    let mut unmatched_new: Vec<&T> = new.iter().collect();
+    let mut diff = ListDiff { added: Vec::new(), removed: Vec::new(), changed: Vec::new() };
+    for old_entry in old.iter() {
+        match unmatched_new.iter().position(|new_entry| key(new_entry) == key(old_entry)) {
+            Some(i) => {
+                let new_entry = unmatched_new.remove(i);
+                if !same(old_entry, new_entry) {
+                    diff.changed.push(Change { old: old_entry.clone(), new: new_entry.clone() });
+                }
+            },
+            None => diff.removed.push(old_entry.clone()),
+        }
+    }
+    diff.added = unmatched_new.into_iter().cloned().collect();
+    diff
+}
+
+fn diff_by_position<T: Clone + PartialEq>(old: &[T], new: &[T]) -> ListDiff<T> {
+    ListDiff {
+        added: new.iter().skip(old.len()).cloned().collect(),
+        removed: old.iter().skip(new.len()).cloned().collect(),
+        changed: old.iter().zip(new.iter())
+            .filter(|(old_entry, new_entry)| old_entry != new_entry)
+            .map(|(old_entry, new_entry)| Change { old: old_entry.clone(), new: new_entry.clone() })
+            .collect(),
+    }
+}
+
+/// CaveUnit's PartialEq only compares size and door count, which isn't enough here.
+fn same_unit(old: &CaveUnit, new: &CaveUnit) -> bool {
+    old.width == new.width && old.height == new.height && old.room_type == new.room_type && old.doors == new.doors
+}
+
+fn parameter_changes(old: &FloorInfo, new: &FloorInfo) -> Vec<ParameterChange> {
+    let parameters: [(&str, fn(&FloorInfo) -> Option<String>); 18] = [
+        ("sublevel_end", |floor| Some(floor.sublevel_end.to_string())),
+        ("max_main_objects", |floor| Some(floor.max_main_objects.to_string())),
+        ("max_treasures", |floor| Some(floor.max_treasures.to_string())),
+        ("max_gates", |floor| Some(floor.max_gates.to_string())),
+        ("num_rooms", |floor| Some(floor.num_rooms.to_string())),
+        ("corridor_probability", |floor| Some(floor.corridor_probability.to_string())),
+        ("cap_probability", |floor| Some(floor.cap_probability.to_string())),
+        ("has_geyser", |floor| Some(floor.has_geyser.to_string())),
+        ("exit_plugged", |floor| Some(floor.exit_plugged.to_string())),
+        ("is_final_floor", |floor| Some(floor.is_final_floor.to_string())),
+        ("unit_definition_file", |floor| Some(floor.unit_definition_file.clone())),
+        ("lighting_file", |floor| Some(floor.lighting_file.clone())),
+        ("skybox", |floor| floor.skybox.clone()),
+        ("echo", |floor| floor.echo.map(|echo| echo.to_string())),
+        ("music_type", |floor| floor.music_type.map(|music_type| music_type.to_string())),
+        ("hidden_floor", |floor| floor.hidden_floor.map(|hidden_floor| hidden_floor.to_string())),
+        ("waterwraith_timer", |floor| floor.waterwraith_timer.map(|timer| timer.to_string())),
+        ("version", |floor| floor.version.map(|version| version.to_string())),
+    ];
+    let mut changes: Vec<ParameterChange> = parameters.iter()
+        .map(|(name, get)| ParameterChange { name: name.to_string(), old: get(old), new: get(new) })
+        .filter(|change| change.old != change.new)
+        .collect();
+
+    let tags = old.other_tags.keys().chain(new.other_tags.keys().filter(|tag| !old.other_tags.contains_key(*tag)));
+    for tag in tags {
+        let (old_value, new_value) = (old.other_tags.get(tag).cloned(), new.other_tags.get(tag).cloned());
+        if old_value != new_value {
+            changes.push(ParameterChange { name: format!("{{f{}}}", tag), old: old_value, new: new_value });
+        }
+    }
+    changes
+}
+
+
+// Text output, one line per difference:
+//   Floor 3:
+//     max_treasures: 2 -> 3
+//     + teki Kochappy x2 weight 0 group 1
+//     - item ahiru x1 weight 0
+//     ~ gate 500 HP weight 1 -> 1000 HP weight 1
+impl Display for CaveInfoDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        if let Some(Change { old, new }) = &self.num_floors {
+            writeln!(f, "Number of floors: {} -> {}", old, new)?;
+        }
+        for floor in self.floors.iter() {
+            write!(f, "{}", floor)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for FloorInfoDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Floor {}:", self.floor)?;
+        let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        for change in self.parameters.iter() {
+            writeln!(f, "  {}: {} -> {}", change.name, optional(&change.old), optional(&change.new))?;
+        }
+        write_list_diff(f, "teki", &self.teki, describe_teki)?;
+        write_list_diff(f, "item", &self.items, |item| format!("{} x{} weight {}", item.internal_name, item.min_amount, item.filler_distribution_weight))?;
+        write_list_diff(f, "gate", &self.gates, |gate| format!("{} HP weight {}", gate.health, gate.spawn_distribution_weight))?;
+        write_list_diff(f, "cap", &self.caps, describe_cap)?;
+        write_list_diff(f, "unit", &self.units, |unit| {
+            format!("{} {}x{} {:?} {} doors", unit.unit_folder_name, unit.width, unit.height, unit.room_type, unit.num_doors)
+        })
+    }
+}
+
+fn write_list_diff<T>(f: &mut Formatter<'_>, kind: &str, diff: &ListDiff<T>, describe: impl Fn(&T) -> String) -> std::fmt::Result {
+    for added in diff.added.iter() {
+        writeln!(f, "  + {} {}", kind, describe(added))?;
+    }
+    for removed in diff.removed.iter() {
+        writeln!(f, "  - {} {}", kind, describe(removed))?;
+    }
+    for Change { old, new } in diff.changed.iter() {
+        writeln!(f, "  ~ {} {} -> {}", kind, describe(old), describe(new))?;
+    }
+    Ok(())
+}
+
+fn describe_teki(teki: &TekiInfo) -> String {
+    let mut description = teki.internal_name.clone();
+    if let Some(carrying) = &teki.carrying {
+        description.push_str(&format!(" carrying {}", carrying));
+    }
+    if let Some(spawn_method) = &teki.spawn_method {
+        description.push_str(&format!(" spawn method {}", spawn_method));
+    }
+    format!("{} x{} weight {} group {}", description, teki.minimum_amount, teki.filler_distribution_weight, teki.group)
+}
+
+fn describe_cap(cap: &CapInfo) -> String {
+    let mut description = cap.internal_name.clone();
+    if let Some(carrying) = &cap.carrying {
+        description.push_str(&format!(" carrying {}", carrying));
+    }
+    if let Some(spawn_method) = &cap.spawn_method {
+        description.push_str(&format!(" spawn method {}", spawn_method));
+    }
+    format!("{} x{} weight {} group {}", description, cap.minimum_amount, cap.filler_distribution_weight, cap.group)
+}
@@ -2,9 +2,11 @@
 
 
 use once_cell::sync::Lazy;
-use std::{borrow::Cow, sync::Mutex};
+use std::{borrow::Cow, collections::HashMap, sync::Mutex};
 use rust_embed::RustEmbed;
 
+use super::CaveInfoError;
+
 #[derive(RustEmbed)]
 #[folder="$CARGO_MANIFEST_DIR/resources"]
 #[prefix="resources/"]
@@ -34,8 +36,8 @@ pub static TREASURES: Lazy<Mutex<Vec<String>>> = Lazy::new(|| {
     Mutex::new(treasure_names)
 });
 
-pub(super) fn cave_name_to_caveinfo_filename(cave_name: &str) -> &'static str {
-    match cave_name.to_ascii_lowercase().as_str() {
+pub(super) fn cave_name_to_caveinfo_filename(cave_name: &str) -> Result<&'static str, CaveInfoError> {
+    let filename = match cave_name.to_ascii_lowercase().as_str() {
         "ec" => "tutorial_1.txt",
         "scx" => "tutorial_2.txt",
         "fc" => "tutorial_3.txt",
@@ -50,21 +52,102 @@ pub(super) fn cave_name_to_caveinfo_filename(cave_name: &str) -> &'static str {
         "coc" => "last_1.txt",
         "hoh" => "last_2.txt",
         "dd" => "last_3.txt",
-        _ => panic!("Unrecognized cave name \"{}\"", cave_name),
+        _ => return Err(CaveInfoError::InvalidCave(cave_name.to_string())),
+    };
+    Ok(filename)
+}
+
+/// Caveripper's own texture for teki that don't have a usable one in the game files.
+/// These are listed in `resources/teki_info.txt`; if it can't be read, the game's
+/// textures are used for everything and `teki_metadata` reports the error.
+pub fn get_special_texture_name(internal_name: &str) -> Option<&'static str> {
+    super::teki_metadata(internal_name).ok()??.texture.as_deref()
+}
+
+/// Texture mappings registered at runtime, keyed by lowercase internal name.
+/// These take priority over both the special textures above and the ones extracted
+/// from the game, which lets romhack teki and treasures be rendered properly.
+static CUSTOM_TEXTURES: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Registers a texture to use for objects with the given internal name. `path` can
+/// point into the embedded `assets/` or `resources/` folders, or to any image file on disk.
+pub fn register_texture_mapping(internal_name: &str, path: &str) {
+    CUSTOM_TEXTURES.lock().unwrap().insert(internal_name.to_ascii_lowercase(), path.to_owned());
+}
+
+pub fn get_custom_texture_path(internal_name: &str) -> Option<String> {
+    CUSTOM_TEXTURES.lock().unwrap().get(&internal_name.to_ascii_lowercase()).cloned()
+}
+
+/// Display name tables for each language, keyed by language code (e.g. "en") and then
+/// by lowercase internal name. Each language's table is `resources/names/<code>.txt`.
+static NAME_TABLES: Lazy<HashMap<String, HashMap<String, String>>> = Lazy::new(|| {
+    Resources::iter()
+        .filter_map(|path| {
+            let language = path.strip_prefix("resources/names/")?.strip_suffix(".txt")?.to_owned();
+            let names = get_resource_file(&path)?
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(|line| line.split_once(','))
+                .map(|(internal_name, display_name)| (internal_name.trim().to_ascii_lowercase(), display_name.trim().to_owned()))
+                .collect();
+            Some((language, names))
+        })
+        .collect()
+});
+
+/// The language used by `display_name`. `None` means internal names are shown as-is.
+static DISPLAY_LANGUAGE: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(Some("en".to_owned())));
+
+/// Display names registered at runtime, keyed by lowercase internal name. Like custom
+/// textures, these take priority over the built-in tables, for romhack teki and treasures.
+static CUSTOM_NAMES: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Codes of every language with a name table, e.g. "en".
+pub fn available_languages() -> Vec<String> {
+    let mut languages: Vec<String> = NAME_TABLES.keys().cloned().collect();
+    languages.sort();
+    languages
+}
+
+/// Sets the language `display_name` uses, or `None` to show internal names instead.
+/// Fails if there's no name table for the language.
+pub fn set_display_language(language: Option<&str>) -> Result<(), String> {
+    let language = language.map(|language| language.to_ascii_lowercase());
+    if let Some(language) = language.as_ref() {
+        if !NAME_TABLES.contains_key(language) {
+            return Err(format!("No names available for language '{}'. Available languages: {}", language, available_languages().join(", ")));
+        }
     }
+    *DISPLAY_LANGUAGE.lock().unwrap() = language;
+    Ok(())
 }
 
-pub fn get_special_texture_name(internal_name: &str) -> Option<&str> {
-    match internal_name.to_ascii_lowercase().as_ref() {
-        "gashiba" => Some("Gas_pipe_icon.png"),
-        "daiodogreen" => Some("daiodogreen.png"),
-        "ooinu_s" => Some("ooinu_s.png"),
-        "kareooinu_s" => Some("kareooinu_s.png"),
-        "kareooinu_l" => Some("kareooinu.png"),
-        "elechiba" => Some("Electrical_wire_icon.png"),
-        "hiba" => Some("Fire_geyser_icon.png"),
-        "bomb" => Some("Bingo_Battle_Bomb_icon.png"),
-        "egg" => Some("36px-Egg_icon.png"),
-        _ => None
+pub fn register_display_name(internal_name: &str, display_name: &str) {
+    CUSTOM_NAMES.lock().unwrap().insert(internal_name.to_ascii_lowercase(), display_name.to_owned());
+}
+
+/// The name of a teki, treasure, or item in a specific language, if it's known.
+pub fn display_name_in(internal_name: &str, language: &str) -> Option<&'static str> {
+    NAME_TABLES.get(&language.to_ascii_lowercase())?
+        .get(&internal_name.to_ascii_lowercase())
+        .map(|name| name.as_str())
+}
+
+/// The human-readable name of a teki, treasure, or item in the current display language.
+/// Falls back to English, and then to the internal name, for anything that hasn't been
+/// translated yet.
+pub fn display_name(internal_name: &str) -> String {
+    let language = match DISPLAY_LANGUAGE.lock().unwrap().clone() {
+        Some(language) => language,
+        None => return internal_name.to_owned(),
+    };
+    if let Some(name) = CUSTOM_NAMES.lock().unwrap().get(&internal_name.to_ascii_lowercase()) {
+        return name.clone();
     }
-}
\ No newline at end of file
+    display_name_in(internal_name, &language)
+        .or_else(|| display_name_in(internal_name, "en"))
+        .unwrap_or(internal_name)
+        .to_owned()
+}
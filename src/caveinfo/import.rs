@@ -0,0 +1,64 @@
+/// JSON and TOML versions of caveinfo, for external tools and hand editing. These are
+/// plain serde dumps of CaveInfo and FloorInfo. When importing, `cave_units` is always
+/// rebuilt from `unit_definitions` so it can be left out of hand-written files.
+
+use super::{expand_rotations, sort_cave_units, CaveInfo, CaveInfoError, FloorInfo};
+
+impl CaveInfo {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("CaveInfo should always serialize to JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<CaveInfo, CaveInfoError> {
+        let caveinfo: CaveInfo = serde_json::from_str(json).map_err(|e| CaveInfoError::ConversionError(e.to_string()))?;
+        Ok(caveinfo.normalized())
+    }
+
+    pub fn to_toml(&self) -> Result<String, CaveInfoError> {
+        to_toml(self)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<CaveInfo, CaveInfoError> {
+        let caveinfo: CaveInfo = toml::from_str(toml).map_err(|e| CaveInfoError::ConversionError(e.to_string()))?;
+        Ok(caveinfo.normalized())
+    }
+
+    fn normalized(mut self) -> CaveInfo {
+        self.floors = self.floors.into_iter().map(FloorInfo::normalized).collect();
+        self
+    }
+}
+
+impl FloorInfo {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("FloorInfo should always serialize to JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<FloorInfo, CaveInfoError> {
+        let floor: FloorInfo = serde_json::from_str(json).map_err(|e| CaveInfoError::ConversionError(e.to_string()))?;
+        Ok(floor.normalized())
+    }
+
+    pub fn to_toml(&self) -> Result<String, CaveInfoError> {
+        to_toml(self)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<FloorInfo, CaveInfoError> {
+        let floor: FloorInfo = toml::from_str(toml).map_err(|e| CaveInfoError::ConversionError(e.to_string()))?;
+        Ok(floor.normalized())
+    }
+
+    /// Sorts and rotates the unit definitions the same way loading from game files does.
+    fn normalized(mut self) -> FloorInfo {
+        self.cave_units = expand_rotations(sort_cave_units(self.unit_definitions.clone()));
+        self
+    }
+}
+
+/// TOML needs plain values written before tables, which field order alone doesn't
+/// guarantee. Going through `toml::Value` sorts that out.
+fn to_toml<T: serde::Serialize>(value: &T) -> Result<String, CaveInfoError> {
+    toml::Value::try_from(value)
+        .and_then(|value| toml::to_string_pretty(&value))
+        .map_err(|e| CaveInfoError::ConversionError(e.to_string()))
+}
@@ -0,0 +1,125 @@
+/// Checks for caveinfo that parses fine but can't work as intended in game, e.g. teki
+/// that don't exist or map units that are missing. Mostly useful for romhacks, since
+/// vanilla caveinfo is known to be fine.
+
+use std::fmt::{Display, Formatter};
+use serde::Serialize;
+
+use crate::assets::get_enemy_list;
+use super::{get_special_texture_name, AssetSource, FloorInfo, TREASURES};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Lint {
+    /// A map unit in the unit definition file has no folder in `assets/arc`.
+    MissingUnit { unit: String },
+    /// A teki with no texture in the game files, which usually means a typo.
+    UnknownTeki { name: String },
+    UnknownTreasure { name: String },
+    /// The minimum amounts of teki in groups 0, 1, 5 and 8 add up to more than
+    /// `max_main_objects`. All of them still spawn, but there's no room for filler teki.
+    MinimumsOverMaxMainObjects { max_main_objects: u32, minimum_total: u32 },
+    /// A teki, treasure, gate or cap teki with a minimum amount and weight of 0.
+    NeverSpawns { name: String },
+    /// Cap teki groups can only be 0 (spawn two at a time) or 1 (spawn one at a time).
+    InvalidCapGroup { name: String, group: u8 },
+    /// None of the floor's map units have a group 7 spawn point for the Research Pod.
+    NoStartSpawnPoint,
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lint::MissingUnit { unit } => write!(f, "map unit '{}' has no folder in assets/arc", unit),
+            Lint::UnknownTeki { name } => write!(f, "unknown teki '{}'", name),
+            Lint::UnknownTreasure { name } => write!(f, "unknown treasure '{}'", name),
+            Lint::MinimumsOverMaxMainObjects { max_main_objects, minimum_total } =>
+                write!(f, "minimum teki amounts add up to {} but max main objects is only {}", minimum_total, max_main_objects),
+            Lint::NeverSpawns { name } => write!(f, "'{}' has a minimum amount and weight of 0 and will never spawn", name),
+            Lint::InvalidCapGroup { name, group } => write!(f, "cap teki '{}' has group {}, which should be 0 or 1", name, group),
+            Lint::NoStartSpawnPoint => write!(f, "no map unit has a start spawn point"),
+        }
+    }
+}
+
+impl FloorInfo {
+    /// Looks for problems with this floor. Map unit folders are looked up in `source`;
+    /// teki are checked against the embedded game files.
+    pub fn lint(&self, source: &AssetSource) -> Vec<Lint> {
+        let mut lints = Vec::new();
+
+        for unit in self.unit_definitions.iter() {
+            if !source.has_folder(&format!("assets/arc/{}", unit.unit_folder_name)) {
+                lints.push(Lint::MissingUnit { unit: unit.unit_folder_name.clone() });
+            }
+        }
+
+        for teki in self.teki_info.iter() {
+            if !is_known_teki(&teki.internal_name) {
+                lints.push(Lint::UnknownTeki { name: teki.internal_name.clone() });
+            }
+            if let Some(carrying) = teki.carrying.as_ref().filter(|carrying| !is_known_treasure(carrying)) {
+                lints.push(Lint::UnknownTreasure { name: carrying.clone() });
+            }
+            // Decorative teki (group 6) don't have a weight.
+            if teki.minimum_amount == 0 && (teki.filler_distribution_weight == 0 || teki.group == 6) {
+                lints.push(Lint::NeverSpawns { name: teki.internal_name.clone() });
+            }
+        }
+
+        for item in self.item_info.iter() {
+            if !is_known_treasure(&item.internal_name) {
+                lints.push(Lint::UnknownTreasure { name: item.internal_name.clone() });
+            }
+            if item.min_amount == 0 && item.filler_distribution_weight == 0 {
+                lints.push(Lint::NeverSpawns { name: item.internal_name.clone() });
+            }
+        }
+
+        for gate in self.gate_info.iter() {
+            if gate.spawn_distribution_weight == 0 {
+                lints.push(Lint::NeverSpawns { name: format!("gate ({} HP)", gate.health) });
+            }
+        }
+
+        for cap in self.cap_info.iter() {
+            // Loose treasures can spawn in caps too.
+            if !is_known_teki(&cap.internal_name) && !is_known_treasure(&cap.internal_name) {
+                lints.push(Lint::UnknownTeki { name: cap.internal_name.clone() });
+            }
+            if let Some(carrying) = cap.carrying.as_ref().filter(|carrying| !is_known_treasure(carrying)) {
+                lints.push(Lint::UnknownTreasure { name: carrying.clone() });
+            }
+            if cap.minimum_amount == 0 && cap.filler_distribution_weight == 0 {
+                lints.push(Lint::NeverSpawns { name: cap.internal_name.clone() });
+            }
+            if cap.group > 1 {
+                lints.push(Lint::InvalidCapGroup { name: cap.internal_name.clone(), group: cap.group });
+            }
+        }
+
+        // Same groups the generator allocates minimums for.
+        let minimum_total: u32 = [0, 1, 5, 8].into_iter()
+            .flat_map(|group| self.teki_group(group))
+            .map(|teki| teki.minimum_amount)
+            .sum();
+        if minimum_total > self.max_main_objects {
+            lints.push(Lint::MinimumsOverMaxMainObjects { max_main_objects: self.max_main_objects, minimum_total });
+        }
+
+        if !self.cave_units.iter().any(|unit| unit.has_start_spawnpoint()) {
+            lints.push(Lint::NoStartSpawnPoint);
+        }
+
+        lints
+    }
+}
+
+fn is_known_teki(internal_name: &str) -> bool {
+    get_enemy_list().iter().any(|teki| teki.eq_ignore_ascii_case(internal_name))
+        || get_special_texture_name(internal_name).is_some()
+}
+
+fn is_known_treasure(internal_name: &str) -> bool {
+    TREASURES.lock().unwrap().iter().any(|treasure| treasure.eq_ignore_ascii_case(internal_name))
+}
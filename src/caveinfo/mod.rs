@@ -8,32 +8,51 @@
 /// For info on the CaveInfo file format, see
 /// https://pikmintkb.com/wiki/Cave_generation_parameters
 
+mod builder;
 mod caveinfoerror;
 pub mod gamedata;
 mod parse;
 mod caveinfo_lazy_init;
+mod diff;
+mod import;
+mod lint;
+mod sublevel_id;
+mod teki;
+mod treasures;
+mod write;
 
 #[cfg(test)]
 mod test;
 
-pub use caveinfoerror::CaveInfoError;
+pub use builder::FloorInfoBuilder;
+pub use caveinfoerror::{CaveInfoError, ParseError};
 pub use gamedata::*;
 pub use caveinfo_lazy_init::*;
+pub use sublevel_id::{SublevelId, canonical_cave_name};
+pub use teki::{TekiCategory, TekiMetadata, TEKI_METADATA, teki_metadata};
+pub use write::{write_cave_unit_definition, SourceText};
+pub use lint::Lint;
+pub use diff::{CaveInfoDiff, FloorInfoDiff, ListDiff, Change, ParameterChange};
+pub use treasures::{TreasureInfo, TREASURE_INFO, treasure_info};
+pub use crate::assets::{get_unit_definition_list, treasure_texture_folder, AssetSource, Region};
 
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use std::{cmp::Ordering, convert::{TryFrom, TryInto}};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::BTreeMap, convert::{TryFrom, TryInto}};
 
-use crate::assets::get_file_JIS;
 
 
 /// Contains FloorInfo for each sublevel in an entire cave.
 /// This struct isn't used much; see FloorInfo for all the interesting parts.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CaveInfo {
     pub num_floors: u32,
     pub floors: Vec<FloorInfo>,
+    /// The text before and after the floors in the file this was loaded from, if any.
+    #[serde(skip)]
+    pub source_text: Option<SourceText>,
 }
 
 
@@ -42,7 +61,7 @@ pub struct CaveInfo {
 /// the next FloorInfo section begins or the file ends.
 /// Essentially, this is the entire collection of information required to
 /// generate one sublevel.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FloorInfo {
     pub cave_name: Option<String>,  // Not part of the CaveInfo file, just for debugging and logging purposes.
     pub sublevel: u32, // 0-indexed
@@ -54,14 +73,106 @@ pub struct FloorInfo {
     pub cap_probability: f32, // In range [0-1]. (?) Probability of a cap (no spawn point) being generated instead of an alcove (has one spawn point).
     pub has_geyser: bool,
     pub exit_plugged: bool,
-    pub cave_units: Vec<CaveUnit>,
+    #[serde(default)]
+    pub cave_units: Vec<CaveUnit>, // Rebuilt from unit_definitions when importing from JSON or TOML.
+    pub unit_definitions: Vec<CaveUnit>, // The same units as above, but unsorted and unrotated as in the unit definition file.
     pub teki_info: Vec<TekiInfo>,
     pub item_info: Vec<ItemInfo>,
     pub gate_info: Vec<GateInfo>,
     pub cap_info: Vec<CapInfo>,
     pub is_final_floor: bool,
+
+    // The rest of the FloorInfo tags don't affect generation, but are kept so floors can
+    // be fully described (and written back out) without re-reading the raw file.
+    pub sublevel_end: u32,                  // f001. Always the same as `sublevel` in vanilla caves.
+    pub unit_definition_file: String,       // f008
+    pub lighting_file: String,              // f009
+    pub skybox: Option<String>,             // f00A. "none" in the file if there isn't one.
+    pub echo: Option<u32>,                  // f011. Strength of the sound echo effect.
+    pub music_type: Option<u32>,            // f012
+    pub hidden_floor: Option<bool>,         // f013. Whether the floor plane collision is hidden.
+    pub waterwraith_timer: Option<f32>,     // f015. Seconds before the Waterwraith falls, if it's on this floor.
+    pub version: Option<u32>,               // f016
+    #[serde(default)]
+    pub other_tags: BTreeMap<String, String>, // Any tags not listed above, by tag, e.g. "017".
+    #[serde(skip)]
+    pub source_text: Option<SourceText>,     // The text of this floor's sections in the file it was loaded from, if any.
+}
+
+/// Source text is only formatting, so it's left out when comparing caveinfo.
+impl PartialEq for CaveInfo {
+    fn eq(&self, other: &CaveInfo) -> bool {
+        let CaveInfo { num_floors, floors, source_text: _ } = self;
+        *num_floors == other.num_floors && *floors == other.floors
+    }
 }
 
+impl PartialEq for FloorInfo {
+    fn eq(&self, other: &FloorInfo) -> bool {
+        let FloorInfo {
+            cave_name,
+            sublevel,
+            max_main_objects,
+            max_treasures,
+            max_gates,
+            num_rooms,
+            corridor_probability,
+            cap_probability,
+            has_geyser,
+            exit_plugged,
+            cave_units,
+            unit_definitions,
+            teki_info,
+            item_info,
+            gate_info,
+            cap_info,
+            is_final_floor,
+            sublevel_end,
+            unit_definition_file,
+            lighting_file,
+            skybox,
+            echo,
+            music_type,
+            hidden_floor,
+            waterwraith_timer,
+            version,
+            other_tags,
+            source_text: _,
+        } = self;
+        *cave_name == other.cave_name
+            && *sublevel == other.sublevel
+            && *max_main_objects == other.max_main_objects
+            && *max_treasures == other.max_treasures
+            && *max_gates == other.max_gates
+            && *num_rooms == other.num_rooms
+            && *corridor_probability == other.corridor_probability
+            && *cap_probability == other.cap_probability
+            && *has_geyser == other.has_geyser
+            && *exit_plugged == other.exit_plugged
+            && *cave_units == other.cave_units
+            && *unit_definitions == other.unit_definitions
+            && *teki_info == other.teki_info
+            && *item_info == other.item_info
+            && *gate_info == other.gate_info
+            && *cap_info == other.cap_info
+            && *is_final_floor == other.is_final_floor
+            && *sublevel_end == other.sublevel_end
+            && *unit_definition_file == other.unit_definition_file
+            && *lighting_file == other.lighting_file
+            && *skybox == other.skybox
+            && *echo == other.echo
+            && *music_type == other.music_type
+            && *hidden_floor == other.hidden_floor
+            && *waterwraith_timer == other.waterwraith_timer
+            && *version == other.version
+            && *other_tags == other.other_tags
+    }
+}
+
+/// Stands in for `cave_name` on floors that don't have one, e.g. ones imported from
+/// hand-written JSON.
+const UNNAMED_CAVE: &str = "Custom";
+
 impl FloorInfo {
     /// Return all teki in a particular spawn group.
     pub fn teki_group(&self, group: u32) -> impl Iterator<Item=&TekiInfo> {
@@ -75,9 +186,22 @@ impl FloorInfo {
     }
 
     /// Returns the human-readable sublevel name for this floor, e.g. "SCx6".
-    /// Not part of the generation algorithm at all.
+    /// Not part of the generation algorithm at all. Floors imported without a cave
+    /// name are called "Custom", e.g. "Custom1".
     pub fn name(&self) -> String {
-        format!("{}{}", self.cave_name.as_ref().expect("No cave name found!"), self.sublevel+1)
+        format!("{}{}", self.cave_name.as_deref().unwrap_or(UNNAMED_CAVE), self.sublevel+1)
+    }
+
+    /// Whether the Waterwraith can appear on this floor.
+    pub fn has_waterwraith(&self) -> bool {
+        self.teki_info.iter().any(|teki| teki.internal_name.eq_ignore_ascii_case("blackman"))
+    }
+
+    pub fn sublevel_id(&self) -> SublevelId {
+        SublevelId {
+            cave: self.cave_name.clone().unwrap_or_else(|| UNNAMED_CAVE.to_string()),
+            floor: self.sublevel + 1,
+        }
     }
 }
 
@@ -89,7 +213,7 @@ impl FloorInfo {
 /// that aren't either treasures or gates are considered Teki.
 /// Treasures held inside enemies *are* defined in TekiInfo, however. See the
 /// `carrying` field.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TekiInfo {
     pub internal_name: String,
     pub carrying: Option<String>, // The object held by this Teki, if any.
@@ -102,7 +226,7 @@ pub struct TekiInfo {
 
 /// Defines 'loose' treasures, i.e. those that are not held by an enemy, but
 /// rather sitting out in the open or buried.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ItemInfo {
     pub internal_name: String,
     pub min_amount: u8,
@@ -111,7 +235,7 @@ pub struct ItemInfo {
 
 
 /// Defines gates. Very straightforward.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GateInfo {
     pub health: f32,
     pub spawn_distribution_weight: u32, // https://pikmintkb.com/wiki/Cave_spawning#Weighted_distribution
@@ -135,7 +259,7 @@ pub struct GateInfo {
 /// with a spawn point' and "cap" when they mean 'a dead end with no spawn point'.
 /// CapInfo only applies to the former, 'dead ends with spawn points' A.K.A.
 /// "alcoves". Nothing can spawn in "caps" as you might expect.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CapInfo {
     pub internal_name: String,
     pub carrying: Option<String>, // The object held by this Cap Teki, if any.
@@ -143,6 +267,8 @@ pub struct CapInfo {
     pub filler_distribution_weight: u32, // https://pikmintkb.com/wiki/Cave_spawning#Weighted_distribution
     pub group: u8,                      // Does not control spawn location like it does in TekiInfo.
     pub spawn_method: Option<String>, // https://pikmintkb.com/wiki/Cave_generation_parameters#Spawn_method
+    #[serde(default)]
+    pub cap_type: u32,                  // The first line of each entry. Not used for generation; 0 in vanilla files.
 }
 
 impl CapInfo {
@@ -166,7 +292,7 @@ impl CapInfo {
 /// generated on a given sublevel. Each CaveUnit represents one possible
 /// map tile.
 /// https://pikmintkb.com/wiki/Cave_unit_definition_file
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CaveUnit {
     pub unit_folder_name: String,
     pub width: u16,  // In cave grid cells, not in-game coords
@@ -176,6 +302,10 @@ pub struct CaveUnit {
     pub doors: Vec<DoorUnit>,
     pub rotation: u16,
     pub spawn_points: Vec<SpawnPoint>,
+    #[serde(default)]
+    pub version: u32,    // The first line of the unit definition. Not used for generation.
+    #[serde(default)]
+    pub flags: Vec<u32>, // The line after the room type. Not used for generation.
 }
 
 
@@ -237,6 +367,45 @@ impl CaveUnit {
     }
 }
 
+impl CaveInfo {
+    /// Loads and parses a cave's CaveInfo file along with all of its map units, e.g.
+    /// `CaveInfo::load(&AssetSource::Embedded(Region::Us), "SCx")`. Any cave name
+    /// accepted by `canonical_cave_name` works. Problems with the files are returned as
+    /// errors rather than panicking, so modded files can be loaded safely.
+    pub fn load(source: &AssetSource, cave: &str) -> Result<CaveInfo, CaveInfoError> {
+        let cave_name = canonical_cave_name(cave).ok_or_else(|| CaveInfoError::InvalidCave(cave.to_string()))?;
+        let caveinfo_filename = format!("assets/caveinfo/{}", cave_name_to_caveinfo_filename(cave_name)?);
+        let caveinfo_txt = source.get_file_JIS(&caveinfo_filename)?;
+
+        let mut result = parse::parse_caveinfo(&caveinfo_txt)
+            .and_then(|floor_chunks| CaveInfo::from_sections(floor_chunks, source))
+            .map_err(|e| e.in_file(&caveinfo_filename))?;
+        for sublevel in result.floors.iter_mut() {
+            sublevel.cave_name = Some(cave_name.to_owned());
+        }
+        Ok(result)
+    }
+}
+
+/// Loads every map unit from a Cave Unit Definition file in `assets/units`, e.g.
+/// "tsuchi_conc_units.txt". Units are returned in file order with no rotations
+/// applied, unlike the ones in FloorInfo which are sorted and expanded for generation.
+pub fn load_cave_units(unit_definition_file_name: &str) -> Result<Vec<CaveUnit>, CaveInfoError> {
+    load_cave_units_from(&AssetSource::Embedded(Region::Us), unit_definition_file_name)
+}
+
+/// Same as `load_cave_units`, but reads the definition file and each unit's layout
+/// file from `source`.
+pub fn load_cave_units_from(source: &AssetSource, unit_definition_file_name: &str) -> Result<Vec<CaveUnit>, CaveInfoError> {
+    let cave_unit_definition_path = format!("assets/units/{}", unit_definition_file_name);
+    let cave_unit_definition_text = source.get_file_JIS(&cave_unit_definition_path)?;
+    parse::parse_cave_unit_definition(&cave_unit_definition_text)
+        .and_then(|cave_unit_sections| {
+            cave_unit_sections.into_iter().map(|section| CaveUnit::from_section(section, source)).collect()
+        })
+        .map_err(|e| e.in_file(&cave_unit_definition_path))
+}
+
 /// The sorting algorithm required by the generation algorithm for cave units.
 /// This sort is unstable! Despite being essentially a bubble sort, I've
 /// implemented it manually here to ensure it exactly matches the one in Pikmin 2.
@@ -272,7 +441,7 @@ fn expand_rotations(input: Vec<CaveUnit>) -> Vec<CaveUnit> {
 /// Indicates position and other metadata about doors in each map unit, relative to its
 /// origin point. A 'door' is just an open spot in a map unit where other map units get
 /// connected. All doors are exactly 170 in-game units wide, i.e. 1 map unit.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DoorUnit {
     pub direction: u16,         // 0, 1, 2, or 3
     pub side_lateral_offset: u16, // Appears to be the offset from center on the side of the room it's facing?
@@ -292,7 +461,7 @@ impl DoorUnit {
 /// DoorLink for every unique pair of doors in a given room tile. These are primarily
 /// used for calculating Door Score.
 /// To clarify, DoorLinks are NOT links between two doors in separate rooms.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DoorLink {
     pub distance: f32,  // Straight line distance. This can cross out-of-bounds and otherwise uncrossable obstacles.
     pub door_id: usize, // Id of the other door
@@ -300,20 +469,21 @@ pub struct DoorLink {
 }
 
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum RoomType {
     Room,
     Hallway,
     DeadEnd,
 }
 
-impl From<usize> for RoomType {
-    fn from(roomtype: usize) -> RoomType {
+impl TryFrom<usize> for RoomType {
+    type Error = CaveInfoError;
+    fn try_from(roomtype: usize) -> Result<RoomType, CaveInfoError> {
         match roomtype {
-            0 => RoomType::DeadEnd,
-            1 => RoomType::Room,
-            2 => RoomType::Hallway,
-            _ => panic!("Invalid room type specified"),
+            0 => Ok(RoomType::DeadEnd),
+            1 => Ok(RoomType::Room),
+            2 => Ok(RoomType::Hallway),
+            _ => Err(CaveInfoError::ParseValueError),
         }
     }
 }
@@ -321,7 +491,7 @@ impl From<usize> for RoomType {
 
 /// Spawn Points for everything that gets placed in sublevels, including the Research
 /// Pod, the exit hole/geyser, treasures, Teki, etc.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpawnPoint {
     pub group: u16,
     pub pos_x: f32,  // Positions are all relative to the origin of the unit they belong to, NOT global coords.
@@ -9,6 +9,7 @@ use nom::{
         alpha1, char, digit1, hex_digit1, line_ending, multispace0, multispace1, not_line_ending,
     },
     combinator::{into, opt, success, value},
+    error::ErrorKind,
     multi::{count, many1},
     sequence::{delimited, preceded, tuple},
     IResult,
@@ -17,91 +18,128 @@ use std::str::FromStr;
 
 /// Takes the entire raw text of a CaveInfo file and parses it into a
 /// CaveInfo struct, ready for passing to the generator.
-pub(super) fn parse_caveinfo(caveinfo_txt: &str) -> IResult<&str, Vec<[Section; 5]>> {
+pub(super) fn parse_caveinfo(caveinfo_txt: &str) -> Result<Vec<[Section; 5]>, CaveInfoError> {
     // Header section
-    let (rest, header_section) = section(caveinfo_txt)?;
-    let num_floors: u8 = header_section
-        .get_tag("000")
-        .expect("Couldn't parse number of floors in CaveInfo header!");
+    let (rest, header_section) = section(caveinfo_txt).map_err(|e| nom_error(caveinfo_txt, e))?;
+    let header_section = header_section.with_source(caveinfo_txt);
+    let num_floors: u8 = header_section.get_tag("000")?;
 
     // CaveInfo files have one unique line after the header section that
     // repeats the floor number before the #FloorInfo comment. This skips
     // that line.
-    let (rest, _) = skip_lines(rest, 1)?;
+    let (rest, _) = skip_lines(rest, 1).map_err(|e| nom_error(caveinfo_txt, e))?;
 
     // Read the five sections for each floor in the cave.
-    let (_, sections) = count(section, 5 * num_floors as usize)(rest)?;
+    let (after_sections, sections) = count(section, 5 * num_floors as usize)(rest).map_err(|e| nom_error(caveinfo_txt, e))?;
+    let sections: Vec<Section> = sections.into_iter().map(|section| section.with_source(caveinfo_txt)).collect();
     let (floor_chunks, remainder): (&[[Section; 5]], &[_]) = sections.as_chunks::<5>();
-    assert_eq!(
-        remainder.len(),
-        0,
-        "CaveInfo files need to have exactly 5 sections per sublevel."
-    );
+    if !remainder.is_empty() {
+        return Err(ParseError::new(caveinfo_txt, after_sections, "exactly 5 sections per sublevel").into());
+    }
 
-    Ok(("", floor_chunks.to_vec()))
+    Ok(floor_chunks.to_vec())
 }
 
 pub(super) fn parse_cave_unit_definition(
     cave_unit_definition_txt: &str,
-) -> IResult<&str, Vec<Section>> {
-    // Skip the 5 comment lines at the top of each file
-    let (cave_unit_definition_txt, ()) = skip_lines(cave_unit_definition_txt, 5)?;
-
-    let (rest, (num_units_str, _, _)) =
-        tuple((digit1, multispace1, line_comment))(cave_unit_definition_txt)?;
-    let num_units: usize = num_units_str
-        .parse()
-        .expect("Couldn't parse num units from Cave Unit Definition File!");
+) -> Result<Vec<Section>, CaveInfoError> {
+    parse_counted_sections(cave_unit_definition_txt, 5, "the number of units")
+}
 
-    count(section, num_units)(rest)
+pub(super) fn parse_cave_unit_layout_file(cave_unit_layout_file_txt: &str) -> Result<Vec<Section>, CaveInfoError> {
+    // The first line is just a comment containing "BaseGen file"
+    parse_counted_sections(cave_unit_layout_file_txt, 1, "the number of spawn points")
 }
 
-pub(super) fn parse_cave_unit_layout_file(cave_unit_layout_file_txt: &str) -> IResult<&str, Vec<Section>> {
-    // Skip the first line, which is just a comment containing "BaseGen file"
-    let (cave_unit_layout_file_txt, ()) = skip_lines(cave_unit_layout_file_txt, 1)?;
+/// Skips the comment lines at the top of a file, then reads a count line followed by
+/// that many sections.
+fn parse_counted_sections<'a>(txt: &'a str, comment_lines: usize, count_description: &str) -> Result<Vec<Section<'a>>, CaveInfoError> {
+    let (rest, ()) = skip_lines(txt, comment_lines).map_err(|e| nom_error(txt, e))?;
 
-    let (rest, (num_gens_str, _, _)) = tuple((digit1, multispace1, line_comment))(cave_unit_layout_file_txt)?;
-    let num_gens = num_gens_str.parse().expect("Couldn't parse num gens from Cave Unit Layout File!");
+    let (rest, (count_str, _, _)) = tuple((digit1, multispace1, line_comment))(rest)
+        .map_err(|_| ParseError::new(txt, rest, count_description))?;
+    let num_sections: usize = count_str.parse()
+        .map_err(|_| ParseError::new(txt, count_str, count_description))?;
 
-    count(section, num_gens)(rest)
+    let (_, sections) = count(section, num_sections)(rest).map_err(|e| nom_error(txt, e))?;
+    Ok(sections.into_iter().map(|section| section.with_source(txt)).collect())
+}
+
+/// Converts a nom error into a ParseError pointing at wherever nom gave up.
+fn nom_error(source: &str, error: nom::Err<nom::error::Error<&str>>) -> CaveInfoError {
+    let (at, kind) = match error {
+        nom::Err::Error(e) | nom::Err::Failure(e) => (e.input, e.code),
+        nom::Err::Incomplete(_) => (&source[source.len()..], ErrorKind::Eof),
+    };
+    let expected = match kind {
+        ErrorKind::Digit => "a number",
+        ErrorKind::CrLf | ErrorKind::Eof => "another line",
+        _ => "a section enclosed in '{' and '}'",
+    };
+    ParseError::new(source, at, expected).into()
 }
 
 /// One 'section' enclosed by curly brackets in a CaveInfo file.
 #[derive(Clone, Debug)]
 pub(super) struct Section<'a> {
     pub lines: Vec<InfoLine<'a>>,
+    /// The section as written, including the comment line before it.
+    pub text: &'a str,
+    /// The whole file this section is from, for error reporting.
+    source: &'a str,
 }
 
 impl<'a> From<Vec<InfoLine<'a>>> for Section<'a> {
     fn from(vec_of_lines: Vec<InfoLine<'a>>) -> Self {
         Section {
             lines: vec_of_lines,
+            text: "",
+            source: "",
         }
     }
 }
 
 impl<'a> Section<'a> {
-    pub(self) fn get_tagged_line(&self, tag: &str) -> Option<&Vec<&'a str>> {
+    /// Sets the file text this section was parsed from so errors can point into it.
+    pub(self) fn with_source(mut self, source: &'a str) -> Self {
+        self.source = source;
+        self.lines.iter_mut().for_each(|line| line.source = source);
+        self
+    }
+
+    pub(self) fn get_tagged_line(&self, tag: &str) -> Option<&InfoLine<'a>> {
         self.lines
             .iter()
             .filter(|line| line.tag.contains(&tag))
             .next()
-            .map(|line| &line.items)
     }
 
     /// Gets and parses the one useful value out of a tagged CaveInfo line.
     /// See https://pikmintkb.com/wiki/Cave_generation_parameters#FloorInfo
     pub(super) fn get_tag<T: FromStr>(&self, tag: &str) -> Result<T, CaveInfoError> {
-        self.get_tagged_line(tag)
-            .ok_or_else(|| CaveInfoError::NoSuchTag(tag.to_string()))?
-            .get(1)
-            .ok_or_else(|| CaveInfoError::MalformedTagLine(tag.to_string()))?
-            .parse()
-            .map_err(|_| CaveInfoError::ParseValueError)
+        let line = self.get_tagged_line(tag).ok_or_else(|| {
+            // Point at the end of the section, where the tag would have to go.
+            let last_line = self.lines.last().map_or(self.source, |line| line.text);
+            ParseError::new(self.source, last_line, format!("a line tagged {} in this section", tag))
+        })?;
+        let written_tag = line.text.split_whitespace().next().unwrap_or(tag);
+        line.parse_item(1, written_tag)
     }
 
-    pub(super) fn get_line(&self, index: usize) -> Result<&InfoLine, CaveInfoError> {
-        self.lines.get(index).ok_or(CaveInfoError::MalformedLine)
+    /// Same as `get_tag`, but for tags that aren't in every file. `None` if the tag isn't
+    /// there; a tag that's there with a bad value is still an error.
+    pub(super) fn get_optional_tag<T: FromStr>(&self, tag: &str) -> Result<Option<T>, CaveInfoError> {
+        match self.get_tagged_line(tag) {
+            Some(_) => self.get_tag(tag).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub(super) fn get_line(&self, index: usize) -> Result<&InfoLine<'a>, CaveInfoError> {
+        self.lines.get(index).ok_or_else(|| {
+            let last_line = self.lines.last().map_or(self.source, |line| line.text);
+            ParseError::new(self.source, &last_line[last_line.len()..], format!("at least {} lines in this section", index + 1)).into()
+        })
     }
 }
 
@@ -110,14 +148,46 @@ impl<'a> Section<'a> {
 pub(super) struct InfoLine<'a> {
     pub tag: Option<&'a str>,
     pub items: Vec<&'a str>,
+    /// The line as written, without its line ending.
+    pub text: &'a str,
+    source: &'a str,
 }
 
-impl InfoLine<'_> {
-    pub fn get_line_item(&self, item: usize) -> Result<&str, CaveInfoError> {
+impl<'a> InfoLine<'a> {
+    pub fn get_line_item(&self, item: usize) -> Result<&'a str, CaveInfoError> {
         self.items
             .get(item)
             .copied()
-            .ok_or(CaveInfoError::MalformedLine)
+            .ok_or_else(|| {
+                let end_of_line = &self.text[self.text.trim_end().len()..];
+                ParseError::new(self.source, end_of_line, format!("at least {} values on this line", item + 1)).into()
+            })
+    }
+
+    /// Gets and parses one item, reporting `field` as the thing that was expected if
+    /// the item is missing or invalid.
+    pub fn parse_item<T: FromStr>(&self, item: usize, field: &str) -> Result<T, CaveInfoError> {
+        let value = self.get_line_item(item)
+            .map_err(|_| {
+                let end_of_line = &self.text[self.text.trim_end().len()..];
+                ParseError::new(self.source, end_of_line, format!("{} for {}", value_description::<T>(), field))
+            })?;
+        value.parse()
+            .map_err(|_| ParseError::new(self.source, value, format!("{} for {}", value_description::<T>(), field)).into())
+    }
+
+    /// Reports `value`, which has to be part of this line, as invalid.
+    pub fn error_at(&self, value: &str, expected: impl Into<String>) -> CaveInfoError {
+        ParseError::new(self.source, value, expected).into()
+    }
+}
+
+/// How to describe a value of type `T` in error messages.
+fn value_description<T>() -> &'static str {
+    match std::any::type_name::<T>() {
+        "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize" => "a whole number",
+        "f32" | "f64" => "a number",
+        _ => "a value",
     }
 }
 
@@ -126,22 +196,27 @@ impl InfoLine<'_> {
 // **********************************************
 
 fn section(caveinfo_txt: &str) -> IResult<&str, Section> {
-    let (caveinfo_txt, _) = line_comment(caveinfo_txt)?;
-    into(delimited(char('{'), many1(info_line), tag("}\r\n")))(caveinfo_txt)
+    let (rest, _) = line_comment(caveinfo_txt)?;
+    let (rest, mut section): (&str, Section) = into(delimited(char('{'), many1(info_line), tag("}\r\n")))(rest)?;
+    section.text = &caveinfo_txt[..caveinfo_txt.len() - rest.len()];
+    Ok((rest, section))
 }
 
 fn info_line(input: &str) -> IResult<&str, InfoLine> {
-    let (rest, (_, tag, items, _)) = tuple((
-        multispace0,
+    let (input, _) = multispace0(input)?;
+    let (rest, (tag, items)) = tuple((
         opt(info_tag),
         alt((is_not("\r\n}"), success(""))),
-        line_ending,
     ))(input)?;
+    let text = &input[..input.len() - rest.len()];
+    let (rest, _) = line_ending(rest)?;
     Ok((
         rest,
         InfoLine {
             tag,
             items: items.split_whitespace().collect(),
+            text,
+            source: "",
         },
     ))
 }
@@ -169,31 +244,59 @@ fn skip_lines(input: &str, skip: usize) -> IResult<&str, ()> {
 impl TryFrom<Vec<[Section<'_>; 5]>> for CaveInfo {
     type Error = CaveInfoError;
     fn try_from(raw_sections: Vec<[parse::Section<'_>; 5]>) -> Result<CaveInfo, CaveInfoError> {
+        CaveInfo::from_sections(raw_sections, &AssetSource::Embedded(Region::Us))
+    }
+}
+
+impl CaveInfo {
+    /// Map units for each floor are loaded from `source`.
+    pub(super) fn from_sections(raw_sections: Vec<[parse::Section<'_>; 5]>, source: &AssetSource) -> Result<CaveInfo, CaveInfoError> {
         let num_floors = raw_sections.len() as u32;
+        // Everything in the file outside the floors' sections.
+        let source_text = match (raw_sections.first(), raw_sections.last()) {
+            (Some([first, ..]), Some([.., last])) => {
+                let file = first.source;
+                let start = text_offset(file, first.text);
+                let end = text_offset(file, last.text).map(|offset| offset + last.text.len());
+                start.zip(end).map(|(start, end)| SourceText::for_caveinfo(&file[..start], &file[end..], num_floors))
+            },
+            _ => None,
+        };
         let mut floors = raw_sections
             .into_iter()
-            .map(TryInto::try_into)
+            .map(|floor_sections| FloorInfo::from_sections(floor_sections, source))
             .collect::<Result<Vec<FloorInfo>, _>>()?;
-        floors.last_mut().unwrap().is_final_floor = true;
+        if let Some(final_floor) = floors.last_mut() {
+            final_floor.is_final_floor = true;
+        }
 
-        Ok(CaveInfo{ num_floors, floors })
+        Ok(CaveInfo{ num_floors, floors, source_text })
     }
 }
 
+/// FloorInfo tags that have their own field in FloorInfo.
+const KNOWN_FLOORINFO_TAGS: [&str; 18] = [
+    "000", "001", "002", "003", "004", "005", "006", "007", "008", "009", "00A",
+    "010", "011", "012", "013", "014", "015", "016",
+];
+
 impl TryFrom<[parse::Section<'_>; 5]> for FloorInfo {
     type Error = CaveInfoError;
     fn try_from(raw_sections: [parse::Section<'_>; 5]) -> Result<FloorInfo, CaveInfoError> {
+        FloorInfo::from_sections(raw_sections, &AssetSource::Embedded(Region::Us))
+    }
+}
+
+impl FloorInfo {
+    pub(super) fn from_sections(raw_sections: [parse::Section<'_>; 5], source: &AssetSource) -> Result<FloorInfo, CaveInfoError> {
+        let section_texts = raw_sections.each_ref().map(|section| section.text);
         let [floorinfo_section, tekiinfo_section, iteminfo_section, gateinfo_section, capinfo_section] =
             raw_sections;
 
         let cave_unit_definition_file_name: String = floorinfo_section.get_tag("008")?;
-        let cave_unit_definition_path = format!("assets/units/{}", &cave_unit_definition_file_name);
-        let cave_unit_definition_text = get_file_JIS(&cave_unit_definition_path)
-            .ok_or(CaveInfoError::MissingFileError(cave_unit_definition_path))?;
-        let (_, cave_unit_sections) = parse_cave_unit_definition(&cave_unit_definition_text)
-            .expect("Couldn't parse Cave Unit Definition file!");
+        let unit_definitions = load_cave_units_from(source, &cave_unit_definition_file_name)?;
 
-        Ok(FloorInfo {
+        let mut floor = FloorInfo {
             cave_name: None,
             sublevel: floorinfo_section.get_tag("000")?,
             max_main_objects: floorinfo_section.get_tag("002")?,
@@ -204,23 +307,42 @@ impl TryFrom<[parse::Section<'_>; 5]> for FloorInfo {
             cap_probability: floorinfo_section.get_tag::<f32>("014")? / 100f32,
             has_geyser: floorinfo_section.get_tag::<u8>("007")? > 0,
             exit_plugged: floorinfo_section.get_tag::<u8>("010")? > 0,
-            cave_units: expand_rotations(
-                sort_cave_units(
-                    cave_unit_sections
-                        .into_iter()
-                        .map(TryInto::try_into)
-                        .collect::<Result<Vec<_>, _>>()?
-                )
-            ),
+            cave_units: expand_rotations(sort_cave_units(unit_definitions.clone())),
+            unit_definitions,
             teki_info: tekiinfo_section.try_into()?,
             item_info: iteminfo_section.try_into()?,
             gate_info: gateinfo_section.try_into()?,
             cap_info: capinfo_section.try_into()?,
             is_final_floor: false,
-        })
+            sublevel_end: floorinfo_section.get_tag("001")?,
+            lighting_file: floorinfo_section.get_tag("009")?,
+            skybox: Some(floorinfo_section.get_tag::<String>("00A")?).filter(|skybox| skybox != "none"),
+            echo: floorinfo_section.get_optional_tag("011")?,
+            music_type: floorinfo_section.get_optional_tag("012")?,
+            hidden_floor: floorinfo_section.get_optional_tag::<u8>("013")?.map(|hidden| hidden > 0),
+            waterwraith_timer: floorinfo_section.get_optional_tag("015")?,
+            version: floorinfo_section.get_optional_tag("016")?,
+            other_tags: floorinfo_section.lines.iter()
+                .filter_map(|line| Some((line.tag?, line.items.get(1)?)))
+                .filter(|(tag, _)| !KNOWN_FLOORINFO_TAGS.contains(tag))
+                .map(|(tag, value)| (tag.to_string(), value.to_string()))
+                .collect(),
+            unit_definition_file: cave_unit_definition_file_name,
+            source_text: None,
+        };
+        if section_texts.iter().all(|text| !text.is_empty()) {
+            floor.source_text = SourceText::for_floor(section_texts, &floor);
+        }
+        Ok(floor)
     }
 }
 
+/// Where `part` starts in `file`, if it's a non-empty slice of it.
+fn text_offset(file: &str, part: &str) -> Option<usize> {
+    let offset = (part.as_ptr() as usize).checked_sub(file.as_ptr() as usize)?;
+    (!part.is_empty() && offset + part.len() <= file.len()).then_some(offset)
+}
+
 impl TryFrom<parse::Section<'_>> for Vec<TekiInfo> {
     type Error = CaveInfoError;
     fn try_from(section: parse::Section) -> Result<Vec<TekiInfo>, CaveInfoError> {
@@ -233,26 +355,28 @@ impl TryFrom<parse::Section<'_>> for Vec<TekiInfo> {
                 |(item_line, group_line)| -> Result<TekiInfo, CaveInfoError> {
                     let internal_identifier = item_line.get_line_item(0)?;
                     let amount_code = item_line.get_line_item(1)?;
-                    let group: u32 = group_line.get_line_item(0)?.parse()?;
+                    let group: u32 = group_line.parse_item(0, "the teki group")?;
 
                     let (spawn_method, internal_name, carrying) =
-                        extract_internal_identifier(internal_identifier);
+                        extract_internal_identifier(internal_identifier)
+                            .ok_or_else(|| item_line.error_at(internal_identifier, "a teki name"))?;
 
                     // Determine amount and filler_distribution_weight based on teki type
                     let minimum_amount: u32;
                     let filler_distribution_weight: u32;
                     if group == 6 {
                         // 6 is the group number for decorative teki
-                        minimum_amount = amount_code.parse()?;
+                        minimum_amount = item_line.parse_item(1, "the teki amount")?;
                         filler_distribution_weight = 0;
                     } else {
                         let (minimum_amount_str, filler_distribution_weight_str) =
-                            amount_code.split_at(amount_code.len() - 1);
+                            split_amount_code(amount_code);
 
                         // If there is only one digit, it represents the filler_distribution_weight
                         // and minimum_amount defaults to 0.
                         minimum_amount = minimum_amount_str.parse().unwrap_or(0);
-                        filler_distribution_weight = filler_distribution_weight_str.parse()?;
+                        filler_distribution_weight = filler_distribution_weight_str.parse()
+                            .map_err(|_| item_line.error_at(amount_code, "an amount code ending in a weight digit"))?;
                     }
 
                     Ok(TekiInfo {
@@ -279,11 +403,12 @@ impl TryFrom<parse::Section<'_>> for Vec<ItemInfo> {
             .map(|line| -> Result<ItemInfo, CaveInfoError> {
                 let amount_code_str = line.get_line_item(1)?;
                 let (min_amount_str, filler_distribution_weight_str) =
-                    amount_code_str.split_at(amount_code_str.len() - 1);
+                    split_amount_code(amount_code_str);
+                let invalid_amount_code = || line.error_at(amount_code_str, "an amount code ending in a weight digit");
                 Ok(ItemInfo {
                     internal_name: line.get_line_item(0)?.to_string(),
-                    min_amount: min_amount_str.parse()?,
-                    filler_distribution_weight: filler_distribution_weight_str.parse()?,
+                    min_amount: min_amount_str.parse().map_err(|_| invalid_amount_code())?,
+                    filler_distribution_weight: filler_distribution_weight_str.parse().map_err(|_| invalid_amount_code())?,
                 })
             })
             .collect()
@@ -300,16 +425,14 @@ impl TryFrom<parse::Section<'_>> for Vec<GateInfo> {
             .tuples()
             .map(
                 |(health_line, spawn_distribution_weight_line)| -> Result<GateInfo, CaveInfoError> {
+                    let weight_str = spawn_distribution_weight_line.get_line_item(0)?;
                     Ok(GateInfo {
-                        health: health_line.get_line_item(1)?.parse()?,
-                        spawn_distribution_weight: spawn_distribution_weight_line
-                            .get_line_item(0)?
+                        health: health_line.parse_item(1, "the gate HP")?,
+                        spawn_distribution_weight: weight_str
                             .chars()
                             .last()
-                            .ok_or(CaveInfoError::MalformedLine)?
-                            .to_digit(10)
-                            .ok_or(CaveInfoError::ParseValueError)?
-                            as u32,
+                            .and_then(|weight| weight.to_digit(10))
+                            .ok_or_else(|| spawn_distribution_weight_line.error_at(weight_str, "a gate weight ending in a digit"))?,
                     })
                 },
             )
@@ -328,21 +451,24 @@ impl TryFrom<parse::Section<'_>> for Vec<CapInfo> {
             .skip(1) // First line contains the number of Teki
             .tuples()
             .map(
-                |(_, item_line, group_line)| -> Result<CapInfo, CaveInfoError> {
+                |(cap_type_line, item_line, group_line)| -> Result<CapInfo, CaveInfoError> {
+                    let cap_type = cap_type_line.parse_item(0, "the cap type")?;
                     let internal_identifier = item_line.get_line_item(0)?;
                     let amount_code = item_line.get_line_item(1)?;
-                    let group: u8 = group_line.get_line_item(0)?.parse()?;
+                    let group: u8 = group_line.parse_item(0, "the cap teki group")?;
 
                     let (spawn_method, internal_name, carrying) =
-                        extract_internal_identifier(internal_identifier);
+                        extract_internal_identifier(internal_identifier)
+                            .ok_or_else(|| item_line.error_at(internal_identifier, "a teki name"))?;
 
                     // Determine amount and filler_distribution_weight based on teki type
                     let (minimum_amount_str, filler_distribution_weight_str) =
-                        amount_code.split_at(amount_code.len() - 1);
+                        split_amount_code(amount_code);
                     // If there is only one digit, it represents the filler_distribution_weight
                     // and minimum_amount defaults to 0.
                     let minimum_amount = minimum_amount_str.parse().unwrap_or(0);
-                    let filler_distribution_weight = filler_distribution_weight_str.parse()?;
+                    let filler_distribution_weight = filler_distribution_weight_str.parse()
+                        .map_err(|_| item_line.error_at(amount_code, "an amount code ending in a weight digit"))?;
 
                     Ok(CapInfo {
                         internal_name,
@@ -351,6 +477,7 @@ impl TryFrom<parse::Section<'_>> for Vec<CapInfo> {
                         filler_distribution_weight,
                         group,
                         spawn_method,
+                        cap_type,
                     })
                 },
             )
@@ -361,19 +488,34 @@ impl TryFrom<parse::Section<'_>> for Vec<CapInfo> {
 impl TryFrom<parse::Section<'_>> for CaveUnit {
     type Error = CaveInfoError;
     fn try_from(section: parse::Section) -> Result<CaveUnit, CaveInfoError> {
+        CaveUnit::from_section(section, &AssetSource::Embedded(Region::Us))
+    }
+}
+
+impl CaveUnit {
+    /// The unit's layout file, if it has one, is read from `source`.
+    pub(super) fn from_section(section: parse::Section, source: &AssetSource) -> Result<CaveUnit, CaveInfoError> {
+        let version = section.get_line(0)?.parse_item(0, "the unit version")?;
+        let flags_line = section.get_line(4)?;
+        let flags = flags_line.items.iter()
+            .take_while(|item| !item.starts_with('#'))
+            .map(|flag| flag.parse().map_err(|_| flags_line.error_at(flag, "a number")))
+            .collect::<Result<Vec<u32>, _>>()?;
         let unit_folder_name = section.get_line(1)?.get_line_item(0)?.to_string();
-        let width = section.get_line(2)?.get_line_item(0)?.parse()?;
-        let height = section.get_line(2)?.get_line_item(1)?.parse()?;
-        let room_type = section
-            .get_line(3)?
-            .get_line_item(0)?
-            .parse::<usize>()?
-            .into();
-        let num_doors = section.get_line(5)?.get_line_item(0)?.parse()?;
+        let width = section.get_line(2)?.parse_item(0, "the unit width")?;
+        let height = section.get_line(2)?.parse_item(1, "the unit height")?;
+        let room_type_line = section.get_line(3)?;
+        let room_type = RoomType::try_from(room_type_line.parse_item::<usize>(0, "the room type")?)
+            .map_err(|_| room_type_line.error_at(room_type_line.items[0], "a room type of 0, 1 or 2"))?;
+        let num_doors_line = section.get_line(5)?;
+        let num_doors = num_doors_line.parse_item(0, "the number of doors")?;
 
         // DoorUnits
         let doors = if num_doors > 0 {
             let num_lines_per_door_unit = (section.lines.len() - 6) / num_doors;
+            if num_lines_per_door_unit < 3 || (section.lines.len() - 6) % num_doors != 0 {
+                return Err(num_doors_line.error_at(num_doors_line.items[0], "a door count matching the door definitions in this section"));
+            }
             section.lines[6..]
                 .chunks(num_lines_per_door_unit)
                 .map(
@@ -387,13 +529,18 @@ impl TryFrom<parse::Section<'_>> for CaveUnit {
         };
 
         // Cave Unit Layout File (spawn points)
-        let mut spawn_points = match get_file_JIS(&format!("assets/arc/{}/texts.d/layout.txt", unit_folder_name)) {
-            Some(cave_unit_layout_file_txt) => {
-                let spawn_points_sections = parse_cave_unit_layout_file(&cave_unit_layout_file_txt)
-                    .expect("Couldn't parse cave unit layout file!").1;
-                spawn_points_sections.into_iter().map(TryInto::try_into).collect::<Result<Vec<_>, _>>()?
+        let layout_file_path = format!("assets/arc/{}/texts.d/layout.txt", unit_folder_name);
+        // Units without spawn points don't have a layout file at all.
+        let mut spawn_points = match source.get_file_JIS(&layout_file_path) {
+            Ok(cave_unit_layout_file_txt) => {
+                parse_cave_unit_layout_file(&cave_unit_layout_file_txt)
+                    .and_then(|spawn_points_sections| {
+                        spawn_points_sections.into_iter().map(TryInto::try_into).collect::<Result<Vec<_>, _>>()
+                    })
+                    .map_err(|e| e.in_file(&layout_file_path))?
             },
-            None => Vec::new(),
+            Err(CaveInfoError::MissingFileError(_)) => Vec::new(),
+            Err(e) => return Err(e),
         };
 
         // Add special Hole/Geyser spawnpoints to Cap and Hallway units. These aren't
@@ -425,6 +572,8 @@ impl TryFrom<parse::Section<'_>> for CaveUnit {
             doors,
             rotation: 0,
             spawn_points,
+            version,
+            flags,
         })
     }
 }
@@ -432,10 +581,10 @@ impl TryFrom<parse::Section<'_>> for CaveUnit {
 impl TryFrom<&[parse::InfoLine<'_>]> for DoorUnit {
     type Error = CaveInfoError;
     fn try_from(lines: &[parse::InfoLine]) -> Result<DoorUnit, CaveInfoError> {
-        let direction = lines[1].get_line_item(0)?.parse()?;
-        let side_lateral_offset = lines[1].get_line_item(1)?.parse()?;
-        let waypoint_index = lines[1].get_line_item(2)?.parse()?;
-        let num_links = lines[2].get_line_item(0)?.parse()?;
+        let direction = lines[1].parse_item(0, "the door direction")?;
+        let side_lateral_offset = lines[1].parse_item(1, "the door offset")?;
+        let waypoint_index = lines[1].parse_item(2, "the door waypoint index")?;
+        let num_links = lines[2].parse_item(0, "the number of door links")?;
         let door_links = lines[3..]
             .into_iter()
             .map(|line| line.try_into())
@@ -453,9 +602,9 @@ impl TryFrom<&[parse::InfoLine<'_>]> for DoorUnit {
 impl TryFrom<&parse::InfoLine<'_>> for DoorLink {
     type Error = CaveInfoError;
     fn try_from(line: &parse::InfoLine) -> Result<DoorLink, CaveInfoError> {
-        let distance = line.get_line_item(0)?.parse()?;
-        let door_id = line.get_line_item(1)?.parse()?;
-        let tekiflag = line.get_line_item(2)?.parse::<u8>()? > 0;
+        let distance = line.parse_item(0, "the door link distance")?;
+        let door_id = line.parse_item(1, "the linked door index")?;
+        let tekiflag = line.parse_item::<u8>(2, "the door link teki flag")? > 0;
         Ok(DoorLink {
             distance,
             door_id,
@@ -469,14 +618,14 @@ impl TryFrom<parse::Section<'_>> for SpawnPoint {
     fn try_from(section: parse::Section) -> Result<SpawnPoint, Self::Error> {
         Ok(
             SpawnPoint {
-                group: section.get_line(0)?.get_line_item(0)?.parse()?,
-                pos_x: section.get_line(1)?.get_line_item(0)?.parse()?,
-                pos_y: section.get_line(1)?.get_line_item(1)?.parse()?,
-                pos_z: section.get_line(1)?.get_line_item(2)?.parse()?,
-                angle_degrees: section.get_line(2)?.get_line_item(0)?.parse()?,
-                radius: section.get_line(3)?.get_line_item(0)?.parse()?,
-                min_num: section.get_line(4)?.get_line_item(0)?.parse()?,
-                max_num: section.get_line(5)?.get_line_item(0)?.parse()?,
+                group: section.get_line(0)?.parse_item(0, "the spawn point group")?,
+                pos_x: section.get_line(1)?.parse_item(0, "the spawn point x position")?,
+                pos_y: section.get_line(1)?.parse_item(1, "the spawn point y position")?,
+                pos_z: section.get_line(1)?.parse_item(2, "the spawn point z position")?,
+                angle_degrees: section.get_line(2)?.parse_item(0, "the spawn point angle")?,
+                radius: section.get_line(3)?.parse_item(0, "the spawn point radius")?,
+                min_num: section.get_line(4)?.parse_item(0, "the spawn point minimum")?,
+                max_num: section.get_line(5)?.parse_item(0, "the spawn point maximum")?,
             }
         )
     }
@@ -496,18 +645,13 @@ static INTERNAL_IDENTIFIER_RE: Lazy<Regex> = Lazy::new(|| {
 });
 fn extract_internal_identifier(
     internal_combined_name: &str,
-) -> (Option<String>, String, Option<String>) {
-    let captures = INTERNAL_IDENTIFIER_RE
-        .captures(internal_combined_name)
-        .expect(&format!(
-            "Not able to capture info from combined internal identifier {}!",
-            internal_combined_name
-        ));
+) -> Option<(Option<String>, String, Option<String>)> {
+    let captures = INTERNAL_IDENTIFIER_RE.captures(internal_combined_name)?;
     let spawn_method = captures.get(1)
         .map(|s| s.as_str())
         .and_then(|sm| sm.strip_prefix('$'))
         .map(|s| s.to_string());
-    let mut internal_combined_name = captures.get(2).unwrap().as_str().to_string();
+    let mut internal_combined_name = captures[2].to_string();
     let mut carrying = None;
 
     for treasure_name in TREASURES.lock().unwrap().iter() {
@@ -522,5 +666,11 @@ fn extract_internal_identifier(
         internal_combined_name = internal_combined_name.strip_prefix("F").unwrap().to_string();
     }
 
-    (spawn_method, internal_combined_name, carrying)
+    Some((spawn_method, internal_combined_name, carrying))
+}
+
+/// Splits an amount code into the minimum amount and the single weight digit at the end.
+fn split_amount_code(amount_code: &str) -> (&str, &str) {
+    let weight_start = amount_code.char_indices().last().map_or(0, |(i, _)| i);
+    amount_code.split_at(weight_start)
 }
\ No newline at end of file
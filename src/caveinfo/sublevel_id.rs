@@ -0,0 +1,111 @@
+/// Sublevel identifiers and the many ways people write them. Runners, CaveGen and the
+/// game community all use slightly different spellings ("SCx1", "SCx-1", "scx_1",
+/// "Subterranean Complex 1"), so this accepts any of them and normalizes to one form.
+
+use std::{fmt::{Display, Formatter}, str::FromStr};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Serialize, Serializer};
+
+use super::{CaveInfoError, FloorInfo, ALL_SUBLEVELS_MAP};
+
+/// Canonical short name, the name CaveGen uses, English name, and number of floors
+/// for every story mode cave.
+const CAVES: [(&str, &str, &str, u32); 14] = [
+    ("EC", "EC", "Emergence Cave", 2),
+    ("SCx", "SCx", "Subterranean Complex", 9),
+    ("FC", "FC", "Frontier Cavern", 8),
+    ("HoB", "HoB", "Hole of Beasts", 5),
+    ("WFG", "WFG", "White Flower Garden", 5),
+    ("SH", "SH", "Snagret Hole", 7),
+    ("BK", "BK", "Bulblax Kingdom", 7),
+    ("CoS", "CoS", "Citadel of Spiders", 5),
+    ("GK", "GK", "Glutton's Kitchen", 6),
+    ("SR", "SR", "Shower Room", 6),
+    ("SmC", "SC", "Submerged Castle", 5),
+    ("CoC", "CoC", "Cavern of Chaos", 10),
+    ("DD", "DD", "Dream Den", 14),
+    ("HoH", "HoH", "Hole of Heroes", 15),
+];
+
+static SUBLEVEL_ID_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*(.*?)[\s_-]*(\d+)\s*$").unwrap());
+
+
+/// A single sublevel, e.g. SCx1. `cave` is always the canonical short name used by
+/// caveripper ("SCx", "SmC") and `floor` starts at 1.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SublevelId {
+    pub cave: String,
+    pub floor: u32,
+}
+
+impl SublevelId {
+    /// The form CaveGen expects on its command line, e.g. "SCx-1" or "SC-3".
+    pub fn cavegen_name(&self) -> String {
+        let cavegen_cave = CAVES.iter()
+            .find(|(short_name, ..)| *short_name == self.cave)
+            .map_or(self.cave.as_str(), |(_, cavegen_name, ..)| cavegen_name);
+        format!("{}-{}", cavegen_cave, self.floor)
+    }
+
+    pub fn english_name(&self) -> Option<String> {
+        CAVES.iter()
+            .find(|(short_name, ..)| *short_name == self.cave)
+            .map(|(_, _, english_name, _)| format!("{} {}", english_name, self.floor))
+    }
+
+    /// Errors if the sublevel doesn't exist or its cave's files couldn't be loaded.
+    pub fn caveinfo(&self) -> Result<&'static FloorInfo, CaveInfoError> {
+        ALL_SUBLEVELS_MAP.get(&self.to_string().to_ascii_lowercase())
+            .ok_or_else(|| CaveInfoError::InvalidSublevel(self.to_string()))?
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+}
+
+impl FromStr for SublevelId {
+    type Err = CaveInfoError;
+
+    fn from_str(s: &str) -> Result<SublevelId, CaveInfoError> {
+        let captures = SUBLEVEL_ID_RE.captures(s)
+            .ok_or_else(|| CaveInfoError::InvalidSublevel(s.to_string()))?;
+        let cave = canonical_cave_name(&captures[1])
+            .ok_or_else(|| CaveInfoError::InvalidSublevel(s.to_string()))?;
+        let floor: u32 = captures[2].parse()
+            .map_err(|_| CaveInfoError::InvalidSublevel(s.to_string()))?;
+
+        let num_floors = CAVES.iter().find(|(short_name, ..)| *short_name == cave).unwrap().3;
+        if floor == 0 || floor > num_floors {
+            return Err(CaveInfoError::InvalidSublevel(s.to_string()));
+        }
+        Ok(SublevelId { cave: cave.to_string(), floor })
+    }
+}
+
+impl Display for SublevelId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.cave, self.floor)
+    }
+}
+
+impl Serialize for SublevelId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+
+/// Finds the canonical short name for a cave given its short name, CaveGen name, or
+/// English name, ignoring case, spaces and punctuation. E.g. "submerged castle",
+/// "sc" and "SmC" all give "SmC".
+pub fn canonical_cave_name(name: &str) -> Option<&'static str> {
+    let simplify = |s: &str| -> String {
+        s.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_ascii_lowercase()
+    };
+    let name = simplify(name);
+    CAVES.iter()
+        .find(|(short_name, cavegen_name, english_name, _)| {
+            name == simplify(short_name) || name == simplify(cavegen_name) || name == simplify(english_name)
+        })
+        .map(|(short_name, ..)| *short_name)
+}
@@ -0,0 +1,132 @@
+/// Per-teki metadata that isn't part of caveinfo: what kind of thing each teki is, what
+/// it drops, and how its corpse is carried. Read from `resources/teki_info.txt`. Any
+/// value that isn't listed there is `None`, as is everything for unlisted teki.
+
+use std::{collections::HashMap, fmt::{Display, Formatter}, str::FromStr};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use super::{display_name, get_resource_file, CapInfo, CaveInfoError, ParseError, TekiInfo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TekiCategory {
+    Enemy,
+    Hazard,
+    Plant,
+    Egg,
+    Candypop,
+}
+
+impl TekiCategory {
+    pub const ALL: [TekiCategory; 5] = [TekiCategory::Enemy, TekiCategory::Hazard, TekiCategory::Plant, TekiCategory::Egg, TekiCategory::Candypop];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TekiCategory::Enemy => "enemy",
+            TekiCategory::Hazard => "hazard",
+            TekiCategory::Plant => "plant",
+            TekiCategory::Egg => "egg",
+            TekiCategory::Candypop => "candypop",
+        }
+    }
+}
+
+impl Display for TekiCategory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for TekiCategory {
+    type Err = String;
+    fn from_str(category: &str) -> Result<TekiCategory, String> {
+        TekiCategory::ALL.iter()
+            .find(|c| c.name().eq_ignore_ascii_case(category))
+            .copied()
+            .ok_or_else(|| format!("Unknown teki category '{}'", category))
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TekiMetadata {
+    pub internal_name: String,
+    pub category: Option<TekiCategory>,
+    pub hp: Option<f32>,
+    /// Pokos dropped when defeated.
+    pub pokos: Option<u32>,
+    /// Number of pellets dropped when defeated.
+    pub pellets: Option<u32>,
+    pub corpse_weight: Option<f32>,
+    pub corpse_min_carriers: Option<u32>,
+    pub corpse_max_carriers: Option<u32>,
+    /// Whether the teki has to be dealt with to get past it, e.g. a hazard in a hallway.
+    pub blocks_path: Option<bool>,
+    /// Texture in `resources/enemytex_special` to use instead of the game's own.
+    pub texture: Option<String>,
+}
+
+impl TekiMetadata {
+    pub fn display_name(&self) -> String {
+        display_name(&self.internal_name)
+    }
+}
+
+/// Metadata for every teki listed in `resources/teki_info.txt`, keyed by lowercase
+/// internal name.
+pub static TEKI_METADATA: Lazy<Result<HashMap<String, TekiMetadata>, CaveInfoError>> = Lazy::new(|| {
+    let text = get_resource_file("resources/teki_info.txt")
+        .ok_or_else(|| CaveInfoError::MissingFileError("resources/teki_info.txt".to_string()))?;
+    parse_teki_metadata(&text).map_err(|e| e.in_file("resources/teki_info.txt"))
+});
+
+pub(super) fn parse_teki_metadata(text: &str) -> Result<HashMap<String, TekiMetadata>, CaveInfoError> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let columns: Vec<&str> = line.split(',').map(|column| column.trim()).collect();
+            let metadata = TekiMetadata {
+                internal_name: columns[0].to_owned(),
+                category: parse_column(text, &columns, 1, "enemy, hazard, plant, egg, or candypop")?,
+                hp: parse_column(text, &columns, 2, "a number for HP")?,
+                pokos: parse_column(text, &columns, 3, "a whole number of Pokos")?,
+                pellets: parse_column(text, &columns, 4, "a whole number of pellets")?,
+                corpse_weight: parse_column(text, &columns, 5, "a number for the corpse weight")?,
+                corpse_min_carriers: parse_column(text, &columns, 6, "a whole number of carriers")?,
+                corpse_max_carriers: parse_column(text, &columns, 7, "a whole number of carriers")?,
+                blocks_path: parse_column(text, &columns, 8, "true or false")?,
+                texture: parse_column(text, &columns, 9, "a file name")?,
+            };
+            Ok((columns[0].to_ascii_lowercase(), metadata))
+        })
+        .collect()
+}
+
+/// Blank or missing columns are `None`.
+fn parse_column<T: FromStr>(text: &str, columns: &[&str], i: usize, expected: &str) -> Result<Option<T>, CaveInfoError> {
+    match columns.get(i).filter(|value| !value.is_empty()) {
+        Some(value) => value.parse().map(Some).map_err(|_| ParseError::new(text, value, expected).into()),
+        None => Ok(None),
+    }
+}
+
+/// Looks up a teki by internal name, ignoring case. Errors if `teki_info.txt` couldn't
+/// be read.
+pub fn teki_metadata(internal_name: &str) -> Result<Option<&'static TekiMetadata>, CaveInfoError> {
+    let teki_metadata = TEKI_METADATA.as_ref().map_err(Clone::clone)?;
+    Ok(teki_metadata.get(&internal_name.to_ascii_lowercase()))
+}
+
+impl TekiInfo {
+    pub fn metadata(&self) -> Result<Option<&'static TekiMetadata>, CaveInfoError> {
+        teki_metadata(&self.internal_name)
+    }
+}
+
+impl CapInfo {
+    pub fn metadata(&self) -> Result<Option<&'static TekiMetadata>, CaveInfoError> {
+        teki_metadata(&self.internal_name)
+    }
+}
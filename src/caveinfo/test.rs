@@ -1,10 +1,400 @@
+use std::convert::TryFrom;
+use itertools::Itertools;
+use encoding_rs::SHIFT_JIS;
 use once_cell::sync::Lazy;
-use super::ALL_SUBLEVELS;
+use crate::assets::get_file_JIS;
+use crate::layout::Layout;
+use super::parse::{parse_caveinfo, parse_cave_unit_definition};
+use super::teki::parse_teki_metadata;
+use super::treasures::parse_treasure_values;
+use super::{AssetSource, Region, treasure_texture_folder, FloorInfo, FloorInfoBuilder, GateInfo, ItemInfo, SH6, CapInfo, CaveInfo, CaveInfoError, Lint, TekiInfo, EC1, ParseError, CaveUnit, SublevelId, get_unit_definition_list, load_cave_units, load_cave_units_from, write_cave_unit_definition, cave_name_to_caveinfo_filename, TekiCategory, ALL_SUBLEVELS, display_name, display_name_in, get_special_texture_name, teki_metadata, treasure_info};
 
 /// Simple check to ensure all caves can be parsed without panicking.
 #[test]
 fn test_caveinfo_parsing() {
     for sublevel_caveinfo in ALL_SUBLEVELS {
-        Lazy::force(sublevel_caveinfo);
+        if let Err(e) = Lazy::force(sublevel_caveinfo) {
+            panic!("{}", e);
+        }
+    }
+}
+
+#[test]
+fn test_sublevel_id_parsing() {
+    for raw in ["scx1", "SCx-1", "scx_1", "SCX 1", "Subterranean Complex 1", "subterranean complex-1"] {
+        let sublevel_id: SublevelId = raw.parse().unwrap();
+        assert_eq!(sublevel_id, SublevelId { cave: "SCx".to_string(), floor: 1 }, "{}", raw);
+    }
+
+    let smc: SublevelId = "SC-3".parse().unwrap();
+    assert_eq!(smc, "SmC3".parse().unwrap());
+    assert_eq!(smc, "Submerged Castle 3".parse().unwrap());
+    assert_eq!(smc.to_string(), "SmC3");
+    assert_eq!(smc.cavegen_name(), "SC-3");
+    assert_eq!("Glutton's Kitchen 6".parse::<SublevelId>().unwrap().to_string(), "GK6");
+    assert_eq!("hoh15".parse::<SublevelId>().unwrap().cavegen_name(), "HoH-15");
+
+    assert!("SCx10".parse::<SublevelId>().is_err());
+    assert!("SH0".parse::<SublevelId>().is_err());
+    assert!("XYZ1".parse::<SublevelId>().is_err());
+    assert!("SCx".parse::<SublevelId>().is_err());
+}
+
+#[test]
+fn test_display_names() {
+    assert_eq!(display_name("kochappy"), "Dwarf Red Bulborb");
+    assert_eq!(display_name("KumaChappy"), "Spotty Bulbear");
+    assert_eq!(display_name_in("dashboots", "en"), Some("Rush Boots"));
+    // Unknown names fall back to the internal name.
+    assert_eq!(display_name("not_a_teki"), "not_a_teki");
+}
+
+#[test]
+fn test_treasure_info() {
+    let rush_boots = treasure_info("dashboots").unwrap().unwrap();
+    assert!(rush_boots.exploration_kit);
+    assert!(!treasure_info("ahiru").unwrap().unwrap().exploration_kit);
+    assert!(treasure_info("not_a_treasure").unwrap().is_none());
+
+    let values = parse_treasure_values("# comment\ntest_a,30,5,10,5.5\ntest_b,,,,\n").unwrap();
+    assert_eq!(values["test_a"], (Some(30), Some(5), Some(10), Some(5.5)));
+    assert_eq!(values["test_b"], (None, None, None, None));
+    match parse_treasure_values("test_a,30,five,10,5") {
+        Err(CaveInfoError::ParseError(ParseError { line, column, text, .. })) => assert_eq!((line, column, text.as_str()), (1, 11, "five")),
+        other => panic!("Expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_teki_metadata() {
+    assert_eq!(teki_metadata("Kochappy").unwrap().unwrap().category, Some(TekiCategory::Enemy));
+    assert_eq!(teki_metadata("gashiba").unwrap().unwrap().category, Some(TekiCategory::Hazard));
+    assert_eq!(get_special_texture_name("kareooinu_l"), Some("kareooinu.png"));
+    assert!(teki_metadata("not_a_teki").unwrap().is_none());
+
+    let metadata = parse_teki_metadata("test_teki,enemy,100,,,5,,10,true,\n").unwrap();
+    assert_eq!((metadata["test_teki"].hp, metadata["test_teki"].corpse_max_carriers, metadata["test_teki"].pokos), (Some(100.0), Some(10), None));
+    match parse_teki_metadata("# comment\ntest_teki,enemy,lots,,,,,,,\n") {
+        Err(CaveInfoError::ParseError(ParseError { line, column, .. })) => assert_eq!((line, column), (2, 17)),
+        other => panic!("Expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_error_location() {
+    let caveinfo_txt = "# CaveInfo\r\n{\r\n\t{c000} 4 x \t# Number of floors\r\n\t{_eof}\r\n}\r\n1\r\n";
+    match parse_caveinfo(caveinfo_txt).map_err(|e| e.in_file("test.txt")) {
+        Err(CaveInfoError::ParseError(ParseError { file, line, column, text, .. })) => {
+            assert_eq!((file.as_deref(), line, column, text.as_str()), (Some("test.txt"), 3, 11, "x"));
+        },
+        other => panic!("Expected a parse error, got {:?}", other.map(|sections| sections.len())),
+    }
+}
+
+/// A teki to add to a vanilla floor, with a weight of 0 and no spawn method.
+fn test_teki(internal_name: &str, carrying: Option<&str>, minimum_amount: u32, group: u32) -> TekiInfo {
+    TekiInfo {
+        internal_name: internal_name.to_string(),
+        carrying: carrying.map(str::to_string),
+        minimum_amount,
+        filler_distribution_weight: 0,
+        group,
+        spawn_method: None,
+    }
+}
+
+/// Same as `test_teki`, for cap teki.
+fn test_cap(internal_name: &str, carrying: Option<&str>, minimum_amount: u32, group: u8) -> CapInfo {
+    CapInfo {
+        internal_name: internal_name.to_string(),
+        carrying: carrying.map(str::to_string),
+        minimum_amount,
+        filler_distribution_weight: 0,
+        group,
+        spawn_method: None,
+        cap_type: 0,
+    }
+}
+
+#[test]
+fn test_lint() {
+    let floor = FloorInfoBuilder::new(EC1.as_ref().unwrap())
+        .max_main_objects(0)
+        .add_teki(test_teki("NotATeki", None, 0, 0))
+        .add_cap(test_cap("Kochappy", Some("not_a_treasure"), 1, 3))
+        .build();
+
+    let lints = floor.lint(&AssetSource::Embedded(Region::Us));
+    for expected in [
+        Lint::UnknownTeki { name: "NotATeki".to_string() },
+        Lint::NeverSpawns { name: "NotATeki".to_string() },
+        Lint::UnknownTreasure { name: "not_a_treasure".to_string() },
+        Lint::InvalidCapGroup { name: "Kochappy".to_string(), group: 3 },
+    ] {
+        assert!(lints.contains(&expected), "Missing {:?} in {:?}", expected, lints);
+    }
+}
+
+#[test]
+fn test_diff() {
+    let old = EC1.as_ref().unwrap();
+    assert!(old.diff(old).is_empty());
+
+    let added_teki = test_teki("Kochappy", Some("not_a_treasure"), 1, 0);
+    let added_gate = GateInfo { health: 1000.0, spawn_distribution_weight: 1 };
+    let new = FloorInfoBuilder::new(old)
+        .max_treasures(old.max_treasures + 1)
+        .add_teki(added_teki.clone())
+        .add_gate(added_gate.clone())
+        .build();
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.parameters.len(), 1);
+    assert_eq!(diff.parameters[0].name, "max_treasures");
+    assert_eq!(diff.teki.added, vec![added_teki.clone()]);
+    assert_eq!(diff.gates.added, vec![added_gate]);
+    assert!(diff.teki.removed.is_empty() && diff.items.is_empty() && diff.caps.is_empty() && diff.units.is_empty());
+
+    let reversed = new.diff(old);
+    assert_eq!(reversed.teki.removed, vec![added_teki]);
+    assert_eq!(reversed.parameters[0].old, Some(new.max_treasures.to_string()));
+}
+
+#[test]
+fn test_floorinfo_builder() {
+    let sh6 = SH6.as_ref().unwrap();
+    assert_eq!(&FloorInfoBuilder::new(sh6).build(), sh6);
+
+    let removed_unit = sh6.unit_definitions[0].unit_folder_name.clone();
+    let floor = FloorInfoBuilder::new(sh6)
+        .max_treasures(3)
+        .remove_unit(&removed_unit).unwrap()
+        .build();
+    assert_eq!(floor.max_treasures, 3);
+    assert!(floor.cave_units.iter().all(|unit| unit.unit_folder_name != removed_unit));
+    assert_eq!(floor.cave_units.len(), floor.unit_definitions.len() * 4);
+    Layout::generate(0x1234ABCD, &floor);
+
+    let readded = FloorInfoBuilder::new(&floor).add_unit(sh6.unit_definitions[0].clone()).unwrap().build();
+    assert_eq!(readded.unit_definitions.len(), sh6.unit_definitions.len());
+
+    let rotated = sh6.cave_units.iter().find(|unit| unit.rotation != 0).unwrap().clone();
+    assert!(matches!(FloorInfoBuilder::new(sh6).add_unit(rotated), Err(CaveInfoError::EditError(_))));
+    assert!(matches!(FloorInfoBuilder::new(sh6).remove_gate(sh6.gate_info.len()), Err(CaveInfoError::EditError(_))));
+
+    // Removing something works once it's there, and is an error otherwise.
+    let with_extras = FloorInfoBuilder::new(sh6)
+        .add_teki(test_teki("NewTeki", None, 1, 0))
+        .add_item(ItemInfo { internal_name: "new_treasure".to_string(), min_amount: 1, filler_distribution_weight: 0 })
+        .add_cap(test_cap("NewCapTeki", None, 1, 0))
+        .build();
+    let removed = FloorInfoBuilder::new(&with_extras)
+        .remove_teki("newteki").unwrap()
+        .remove_item("NEW_TREASURE").unwrap()
+        .remove_cap("NewCapTeki").unwrap()
+        .build();
+    assert_eq!(removed, *sh6);
+    for result in [
+        FloorInfoBuilder::new(sh6).remove_teki("NewTeki"),
+        FloorInfoBuilder::new(sh6).remove_item("new_treasure"),
+        FloorInfoBuilder::new(sh6).remove_cap("NewCapTeki"),
+        FloorInfoBuilder::new(sh6).remove_unit("not_a_unit"),
+        FloorInfoBuilder::new(&floor).remove_unit(&removed_unit),
+    ] {
+        assert!(matches!(result, Err(CaveInfoError::EditError(_))));
+    }
+}
+
+#[test]
+fn test_json_toml_round_trip() {
+    // FloorInfo's PartialEq doesn't look inside map units, so compare what gets written out instead.
+    let floor: &FloorInfo = SH6.as_ref().unwrap();
+    let json = floor.to_json();
+    let toml = floor.to_toml().unwrap();
+    assert_eq!(FloorInfo::from_json(&json).unwrap().to_json(), json);
+    assert_eq!(FloorInfo::from_toml(&toml).unwrap().to_toml().unwrap(), toml);
+    assert_eq!(FloorInfo::from_toml(&toml).unwrap().to_json(), json);
+
+    // cave_units can be left out since it's rebuilt from unit_definitions.
+    let mut without_units: serde_json::Value = serde_json::from_str(&json).unwrap();
+    without_units.as_object_mut().unwrap().remove("cave_units");
+    assert_eq!(FloorInfo::from_json(&without_units.to_string()).unwrap().to_json(), json);
+}
+
+#[test]
+fn test_import_without_cave_name() {
+    let mut json: serde_json::Value = serde_json::from_str(&SH6.as_ref().unwrap().to_json()).unwrap();
+    json.as_object_mut().unwrap().remove("cave_name");
+    let floor = FloorInfo::from_json(&json.to_string()).unwrap();
+    assert_eq!(floor.cave_name, None);
+    assert_eq!(floor.name(), "Custom6");
+    assert_eq!(Layout::generate(0x1234ABCD, &floor).cave_name, "Custom6");
+}
+
+/// Bad or missing files should come back as errors instead of panicking.
+#[test]
+fn test_load_errors() {
+    assert!(matches!(CaveInfo::load(&AssetSource::Embedded(Region::Us), "not a cave"), Err(CaveInfoError::InvalidCave(_))));
+
+    let dir = std::env::temp_dir().join("caveripper_test_load_errors");
+    std::fs::create_dir_all(dir.join("caveinfo")).unwrap();
+    let source = AssetSource::Directory(dir.clone());
+    assert!(matches!(CaveInfo::load(&source, "SCx"), Err(CaveInfoError::MissingFileError(_))));
+
+    std::fs::write(dir.join("caveinfo/tutorial_1.txt"), "# CaveInfo\r\n{\r\n\t{c000} 4 2 \t# Number of floors\r\n\t{_eof}\r\n}\r\n2\r\n# FloorInfo\r\n{\r\n").unwrap();
+    match CaveInfo::load(&source, "EC") {
+        Err(CaveInfoError::ParseError(ParseError { file, line, .. })) => {
+            assert_eq!((file.as_deref(), line), (Some("assets/caveinfo/tutorial_1.txt"), 9));
+        },
+        other => panic!("Expected a parse error, got {:?}", other.map(|caveinfo| caveinfo.num_floors)),
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// A unit definition file with one 2x2 room that has a single door.
+fn test_unit_definition(version: &str, flags: &str) -> String {
+    [
+        "#", "#", "#", "#", "#", "1 \t# Number of units",
+        "# room_a", "{", &format!("\t{}", version), "\troom_a", "\t2 2", "\t1", &format!("\t{}", flags), "\t1",
+        "\t0", "\t0 1 0", "\t0", "}", "",
+    ].join("\r\n")
+}
+
+/// Tags that not every file has can be left out, but not written with a bad value.
+#[test]
+fn test_malformed_optional_tag() {
+    let dir = std::env::temp_dir().join("caveripper_test_malformed_optional_tag");
+    std::fs::create_dir_all(dir.join("caveinfo")).unwrap();
+    std::fs::create_dir_all(dir.join("units")).unwrap();
+    std::fs::write(dir.join("units/test_units.txt"), test_unit_definition("0", "0")).unwrap();
+    let caveinfo = |echo_line: &str| [
+        "# CaveInfo", "{", "\t{c000} 4 1", "\t{_eof}", "}", "1",
+        "# FloorInfo", "{",
+        "\t{f000} 4 0", "\t{f001} 4 0", "\t{f002} 4 10", "\t{f003} 4 2", "\t{f004} 4 1", "\t{f014} 4 50",
+        "\t{f005} 4 3", "\t{f006} 4 0.1", "\t{f007} 4 1", "\t{f008} -1 test_units.txt", "\t{f009} -1 light.ini",
+        "\t{f00A} -1 none", "\t{f010} 4 0", echo_line, "\t{_eof}", "}",
+        "# TekiInfo", "{", "\t0", "}", "# ItemInfo", "{", "\t0", "}", "# GateInfo", "{", "\t0", "}", "# CapInfo", "{", "\t0", "}", "",
+    ].join("\r\n");
+    let source = AssetSource::Directory(dir.clone());
+
+    std::fs::write(dir.join("caveinfo/tutorial_1.txt"), caveinfo("\t{f012} 4 2")).unwrap();
+    let floor = &CaveInfo::load(&source, "EC").unwrap().floors[0];
+    assert_eq!((floor.echo, floor.music_type), (None, Some(2)));
+
+    std::fs::write(dir.join("caveinfo/tutorial_1.txt"), caveinfo("\t{f011} 4 abc")).unwrap();
+    match CaveInfo::load(&source, "EC") {
+        Err(CaveInfoError::ParseError(ParseError { line, text, .. })) => assert_eq!((line, text.as_str()), (22, "abc")),
+        other => panic!("Expected a parse error, got {:?}", other.map(|caveinfo| caveinfo.num_floors)),
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_region_parsing() {
+    for region in Region::ALL {
+        assert_eq!(region.name().parse::<Region>(), Ok(region));
+        assert_eq!(region.game_id().parse::<Region>(), Ok(region));
+    }
+    assert_eq!("NTSC-U".parse::<Region>(), Ok(Region::Us));
+    assert_eq!("europe".parse::<Region>(), Ok(Region::Pal));
+    assert!("kor".parse::<Region>().is_err());
+}
+
+#[test]
+fn test_treasure_texture_folder() {
+    assert_eq!(treasure_texture_folder(Region::Us).unwrap(), "us");
+    // Other regions only have a folder if their assets were extracted, and it's an error
+    // rather than a fallback to the US textures if they weren't.
+    for region in [Region::Pal, Region::Jp] {
+        let resulttex = format!("{}resulttex", region.asset_prefix());
+        match treasure_texture_folder(region) {
+            Ok(folder) => assert!(AssetSource::Embedded(region).has_folder(&format!("{}/{}", resulttex, folder))),
+            Err(CaveInfoError::MissingFileError(path)) => assert_eq!(path, format!("{}/", resulttex)),
+            Err(e) => assert!(matches!(e, CaveInfoError::FileReadError(..)), "{}", e),
+        }
+    }
+}
+
+/// Every vanilla CaveInfo file should be written back exactly as it was, and written
+/// from scratch it should only differ in its comments.
+#[test]
+fn test_caveinfo_round_trip() {
+    let parse = |caveinfo_txt: &str| CaveInfo::try_from(parse_caveinfo(caveinfo_txt).unwrap()).unwrap();
+    for cave in ["EC", "SCx", "FC", "HoB", "WFG", "SH", "BK", "CoS", "GK", "SR", "SmC", "CoC", "DD", "HoH"] {
+        let filename = format!("assets/caveinfo/{}", cave_name_to_caveinfo_filename(cave).unwrap());
+        let original_text = get_file_JIS(&filename, Region::Us).unwrap();
+        let original = parse(&original_text);
+
+        // Unchanged files are written back exactly as they were, comments included.
+        assert_eq!(original.to_caveinfo_text().unwrap(), original_text, "{} didn't round trip", cave);
+        assert_eq!(SHIFT_JIS.decode(&original.to_caveinfo_bytes().unwrap()).0, original_text);
+
+        // Files written from scratch still parse back to the same caveinfo.
+        let mut from_scratch = original.clone();
+        from_scratch.source_text = None;
+        from_scratch.floors.iter_mut().for_each(|floor| floor.source_text = None);
+        let written = from_scratch.to_caveinfo_text().unwrap();
+        assert_eq!(parse(&written), original, "{} didn't round trip", cave);
+        // The writer's own comments aren't the game's, but everything else matches the file.
+        assert_eq!(without_comments(&written), without_comments(&original_text), "{} written from scratch doesn't match", cave);
+
+        // Changed sections are rewritten and the rest are kept as they were.
+        let mut changed = original.clone();
+        changed.floors[0].max_treasures += 1;
+        changed.floors[0].cap_info.push(CapInfo { cap_type: 1, ..test_cap("Kochappy", None, 1, 0) });
+        let written = changed.to_caveinfo_text().unwrap();
+        assert_eq!(parse(&written), changed, "{} didn't round trip", cave);
+    }
+}
+
+/// Each line with any comment and the whitespace before it removed.
+fn without_comments(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim_end())
+        .join("\n")
+}
+
+#[test]
+fn test_write_errors() {
+    let mut caveinfo = CaveInfo { num_floors: 1, floors: vec![EC1.as_ref().unwrap().clone()], source_text: None };
+    caveinfo.floors[0].teki_info[0].filler_distribution_weight = 10;
+    assert!(matches!(caveinfo.to_caveinfo_text(), Err(CaveInfoError::WriteError(_))));
+
+    let rotated = EC1.as_ref().unwrap().cave_units.iter().find(|unit| unit.rotation != 0).unwrap().clone();
+    assert!(matches!(write_cave_unit_definition(&[rotated]), Err(CaveInfoError::WriteError(_))));
+}
+
+#[test]
+fn test_unit_version_and_flags() {
+    let dir = std::env::temp_dir().join("caveripper_test_unit_version_and_flags");
+    std::fs::create_dir_all(dir.join("units")).unwrap();
+    std::fs::write(dir.join("units/test_units.txt"), test_unit_definition("3", "1 2")).unwrap();
+    let units = load_cave_units_from(&AssetSource::Directory(dir.clone()), "test_units.txt").unwrap();
+    assert_eq!((units[0].version, units[0].flags.as_slice()), (3, [1, 2].as_slice()));
+    assert!(write_cave_unit_definition(&units).unwrap().contains("\t3 \t# Version\r\n\troom_a \t# Unit folder name\r\n\t2 2 \t# Width and height\r\n\t1 \t# Room type\r\n\t1 2 \t# Flags\r\n"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_unit_definition_round_trip() {
+    for file_name in get_unit_definition_list() {
+        let original_text = get_file_JIS(&format!("assets/units/{}", file_name), Region::Us).unwrap();
+        let units = load_cave_units(&file_name).unwrap();
+        let written = write_cave_unit_definition(&units).unwrap();
+        // The writer's own comments aren't the game's, but everything else matches the file.
+        assert_eq!(without_comments(&written), without_comments(&original_text), "{}", file_name);
+
+        // CaveUnit's PartialEq only compares size and door count, so check everything else too.
+        let reparsed: Vec<CaveUnit> = parse_cave_unit_definition(&written).unwrap()
+            .into_iter()
+            .map(|section| CaveUnit::try_from(section).unwrap())
+            .collect();
+        assert_eq!(units.len(), reparsed.len(), "{}", file_name);
+        for (unit, reparsed_unit) in units.iter().zip(reparsed.iter()) {
+            assert_eq!(unit.unit_folder_name, reparsed_unit.unit_folder_name, "{}", file_name);
+            assert_eq!((unit.width, unit.height, &unit.room_type), (reparsed_unit.width, reparsed_unit.height, &reparsed_unit.room_type), "{}", file_name);
+            assert_eq!((unit.version, &unit.flags), (reparsed_unit.version, &reparsed_unit.flags), "{}", file_name);
+            assert_eq!(unit.doors, reparsed_unit.doors, "{} in {}", unit.unit_folder_name, file_name);
+        }
     }
 }
@@ -0,0 +1,141 @@
+/// Per-treasure metadata: poko value, carrying requirements, and where each treasure
+/// can be found. Whether a treasure is part of the Exploration Kit comes from data
+/// Caveripper already has; the numeric values are read from
+/// `resources/treasure_info.txt` and are `None` for treasures not listed there. Which
+/// sublevels a treasure spawns on is looked up from the caveinfo when asked for, so
+/// using the table doesn't require loading every cave.
+
+use std::collections::HashMap;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use super::{get_resource_file, CapInfo, CaveInfoError, ItemInfo, ParseError, SublevelId, TekiInfo, ALL_SUBLEVELS, TREASURES};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TreasureInfo {
+    pub internal_name: String,
+    /// Value in Pokos.
+    pub value: Option<u32>,
+    /// Fewest Pikmin that can lift the treasure.
+    pub min_carriers: Option<u32>,
+    /// Most Pikmin that can carry the treasure at once.
+    pub max_carriers: Option<u32>,
+    pub weight: Option<f32>,
+    pub exploration_kit: bool,
+}
+
+impl TreasureInfo {
+    /// Every sublevel the treasure can spawn on, either loose or carried by a teki.
+    /// Empty for treasures that are only found above ground. Loads every cave the first
+    /// time it's called.
+    pub fn sublevels(&self) -> Result<Vec<SublevelId>, CaveInfoError> {
+        let mut sublevels = Vec::new();
+        for floor in ALL_SUBLEVELS.iter() {
+            let floor = floor.as_ref().map_err(Clone::clone)?;
+            let mut treasures = floor.item_info.iter().map(|item| &item.internal_name)
+                .chain(floor.teki_info.iter().filter_map(|teki| teki.carrying.as_ref()))
+                .chain(floor.cap_info.iter().filter_map(|cap| cap.carrying.as_ref()));
+            if treasures.any(|treasure| treasure.eq_ignore_ascii_case(&self.internal_name)) {
+                sublevels.push(floor.sublevel_id());
+            }
+        }
+        Ok(sublevels)
+    }
+
+    /// The cave the treasure is found in, e.g. "SCx", if it's found in one.
+    pub fn cave(&self) -> Result<Option<String>, CaveInfoError> {
+        Ok(self.sublevels()?.into_iter().next().map(|sublevel| sublevel.cave))
+    }
+}
+
+/// Every treasure in the game, keyed by lowercase internal name.
+pub static TREASURE_INFO: Lazy<Result<HashMap<String, TreasureInfo>, CaveInfoError>> = Lazy::new(|| {
+    let exploration_kit: Vec<String> = get_resource_file("resources/treasures_exploration_kit.txt")
+        .ok_or_else(|| CaveInfoError::MissingFileError("resources/treasures_exploration_kit.txt".to_string()))?
+        .lines()
+        .filter_map(|line| line.split_once(','))
+        .map(|(_, name)| name.to_ascii_lowercase())
+        .collect();
+    let values_file = get_resource_file("resources/treasure_info.txt")
+        .ok_or_else(|| CaveInfoError::MissingFileError("resources/treasure_info.txt".to_string()))?;
+    let values = parse_treasure_values(&values_file).map_err(|e| e.in_file("resources/treasure_info.txt"))?;
+
+    let treasure_info = TREASURES.lock().unwrap().iter()
+        .map(|internal_name| {
+            let key = internal_name.to_ascii_lowercase();
+            let (value, min_carriers, max_carriers, weight) = values.get(&key).cloned().unwrap_or_default();
+            let treasure_info = TreasureInfo {
+                internal_name: internal_name.clone(),
+                value,
+                min_carriers,
+                max_carriers,
+                weight,
+                exploration_kit: exploration_kit.contains(&key),
+            };
+            (key, treasure_info)
+        })
+        .collect();
+    Ok(treasure_info)
+});
+
+/// Looks up a treasure by internal name, ignoring case. Errors if the treasure table
+/// couldn't be read.
+pub fn treasure_info(internal_name: &str) -> Result<Option<&'static TreasureInfo>, CaveInfoError> {
+    let treasure_info = TREASURE_INFO.as_ref().map_err(Clone::clone)?;
+    Ok(treasure_info.get(&internal_name.to_ascii_lowercase()))
+}
+
+impl ItemInfo {
+    pub fn treasure_info(&self) -> Result<Option<&'static TreasureInfo>, CaveInfoError> {
+        treasure_info(&self.internal_name)
+    }
+}
+
+impl TekiInfo {
+    /// Info for the treasure this teki carries, if it carries one.
+    pub fn carried_treasure_info(&self) -> Result<Option<&'static TreasureInfo>, CaveInfoError> {
+        match &self.carrying {
+            Some(carrying) => treasure_info(carrying),
+            None => Ok(None),
+        }
+    }
+}
+
+impl CapInfo {
+    /// Info for the treasure this cap teki carries, if it carries one.
+    pub fn carried_treasure_info(&self) -> Result<Option<&'static TreasureInfo>, CaveInfoError> {
+        match &self.carrying {
+            Some(carrying) => treasure_info(carrying),
+            None => Ok(None),
+        }
+    }
+}
+
+type TreasureValues = (Option<u32>, Option<u32>, Option<u32>, Option<f32>);
+
+/// Reads `internal_name,value,min_carriers,max_carriers,weight` lines. Any column
+/// besides the name can be left blank if it isn't known.
+pub(super) fn parse_treasure_values(text: &str) -> Result<HashMap<String, TreasureValues>, CaveInfoError> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let columns: Vec<&str> = line.split(',').map(|column| column.trim()).collect();
+            let values = (
+                parse_column(text, &columns, 1, "a whole number of Pokos")?,
+                parse_column(text, &columns, 2, "a whole number of carriers")?,
+                parse_column(text, &columns, 3, "a whole number of carriers")?,
+                parse_column(text, &columns, 4, "a number for the weight")?,
+            );
+            Ok((columns[0].to_ascii_lowercase(), values))
+        })
+        .collect()
+}
+
+/// Blank or missing columns are `None`.
+fn parse_column<T: std::str::FromStr>(text: &str, columns: &[&str], i: usize, expected: &str) -> Result<Option<T>, CaveInfoError> {
+    match columns.get(i).filter(|column| !column.is_empty()) {
+        Some(column) => column.parse().map(Some).map_err(|_| ParseError::new(text, column, expected).into()),
+        None => Ok(None),
+    }
+}
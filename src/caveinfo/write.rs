@@ -0,0 +1,292 @@
+/// Writing CaveInfo and Cave Unit Definition files back out in the game's own text
+/// format, the inverse of `parse.rs`.
+/// Output uses the same layout the parser expects: CRLF line endings, one comment line
+/// before each section, `{fXXX}` tags in FloorInfo, and combined `$N` spawn method /
+/// carried treasure identifiers in TekiInfo and CapInfo. Caveinfo loaded from a file
+/// remembers the text of each section, and sections that haven't changed since are
+/// written back exactly as they were, comments included. Only changed sections are
+/// written from scratch, so vanilla files round trip byte for byte.
+
+use std::fmt::Write;
+use encoding_rs::SHIFT_JIS;
+use itertools::Itertools;
+
+use super::{CapInfo, CaveInfo, CaveInfoError, CaveUnit, DoorUnit, FloorInfo, GateInfo, ItemInfo, RoomType, TekiInfo};
+
+/// The text something was parsed from, in parts (e.g. the five sections of a floor),
+/// each next to what the writer would produce for it at the time. When the writer's
+/// output for a part still matches, the data hasn't changed and the original text is
+/// used instead. Formatting isn't part of the data, so CaveInfo and FloorInfo leave
+/// this out when they're compared.
+#[derive(Debug, Clone, Default)]
+pub struct SourceText {
+    parts: Vec<(String, String)>,
+}
+
+impl SourceText {
+    pub(super) fn for_caveinfo(header: &str, trailer: &str, num_floors: u32) -> SourceText {
+        SourceText { parts: vec![(header.to_string(), caveinfo_header(num_floors)), (trailer.to_string(), String::new())] }
+    }
+
+    /// `None` if the floor can't be written, since then there's nothing to compare to.
+    pub(super) fn for_floor(sections: [&str; 5], floor: &FloorInfo) -> Option<SourceText> {
+        let written = floor_sections(floor).ok()?;
+        Some(SourceText { parts: sections.iter().map(|section| section.to_string()).zip(written).collect() })
+    }
+
+    fn write_part(source: Option<&SourceText>, index: usize, written: String, out: &mut String) {
+        match source.and_then(|source| source.parts.get(index)) {
+            Some((original, original_written)) if *original_written == written => out.push_str(original),
+            _ => out.push_str(&written),
+        }
+    }
+}
+
+impl CaveInfo {
+    /// The whole CaveInfo file as text. Use `to_caveinfo_bytes` to get a file the game
+    /// can read. Errors if a value can't be represented in the file format.
+    pub fn to_caveinfo_text(&self) -> Result<String, CaveInfoError> {
+        let mut out = String::new();
+        SourceText::write_part(self.source_text.as_ref(), 0, caveinfo_header(self.num_floors), &mut out);
+        for floor in self.floors.iter() {
+            for (i, section) in floor_sections(floor)?.into_iter().enumerate() {
+                SourceText::write_part(floor.source_text.as_ref(), i, section, &mut out);
+            }
+        }
+        SourceText::write_part(self.source_text.as_ref(), 1, String::new(), &mut out);
+        Ok(out)
+    }
+
+    /// The whole CaveInfo file, Shift-JIS encoded like the game's files.
+    pub fn to_caveinfo_bytes(&self) -> Result<Vec<u8>, CaveInfoError> {
+        Ok(SHIFT_JIS.encode(&self.to_caveinfo_text()?).0.into_owned())
+    }
+}
+
+impl FloorInfo {
+    /// This floor's map units as a Cave Unit Definition file, Shift-JIS encoded.
+    pub fn unit_definition_bytes(&self) -> Result<Vec<u8>, CaveInfoError> {
+        Ok(SHIFT_JIS.encode(&write_cave_unit_definition(&self.unit_definitions)?).0.into_owned())
+    }
+}
+
+/// Writes map units as a Cave Unit Definition file, as read by `load_cave_units`. Units
+/// need to be unrotated, e.g. from `FloorInfo::unit_definitions`. Spawn points are stored
+/// in each unit's own layout file rather than here, so they're left out.
+pub fn write_cave_unit_definition(units: &[CaveUnit]) -> Result<String, CaveInfoError> {
+    let mut out = String::new();
+    // The parser skips the first five lines.
+    out.push_str("# Cave Unit Definition File\r\n#\r\n#\r\n#\r\n#\r\n");
+    let _ = write!(out, "{} \t# Number of units\r\n", units.len());
+    for unit in units.iter() {
+        write_cave_unit(&mut out, unit)?;
+    }
+    Ok(out)
+}
+
+fn write_cave_unit(out: &mut String, unit: &CaveUnit) -> Result<(), CaveInfoError> {
+    if unit.rotation != 0 {
+        return Err(CaveInfoError::WriteError(format!("{} is rotated, and only unrotated units can be written to a unit definition file", unit.unit_folder_name)));
+    }
+    let room_type = match unit.room_type {
+        RoomType::DeadEnd => 0,
+        RoomType::Room => 1,
+        RoomType::Hallway => 2,
+    };
+    let _ = write!(out, "# {}\r\n{{\r\n", unit.unit_folder_name);
+    let _ = write!(out, "\t{} \t# Version\r\n", unit.version);
+    let _ = write!(out, "\t{} \t# Unit folder name\r\n", unit.unit_folder_name);
+    let _ = write!(out, "\t{} {} \t# Width and height\r\n", unit.width, unit.height);
+    let _ = write!(out, "\t{} \t# Room type\r\n", room_type);
+    // Units imported without any flags get the usual 0.
+    let flags = if unit.flags.is_empty() { "0".to_string() } else { unit.flags.iter().join(" ") };
+    let _ = write!(out, "\t{} \t# Flags\r\n", flags);
+    let _ = write!(out, "\t{} \t# Number of doors\r\n", unit.num_doors);
+    for (i, door) in unit.doors.iter().enumerate() {
+        write_door(out, i, door);
+    }
+    out.push_str("}\r\n");
+    Ok(())
+}
+
+/// The parser splits doors up by line count, so every door in a unit needs the same
+/// number of links. This is always the case in vanilla units, where every door links to
+/// every other door.
+fn write_door(out: &mut String, index: usize, door: &DoorUnit) {
+    let _ = write!(out, "\t{} \t# Door index\r\n", index);
+    let _ = write!(out, "\t{} {} {} \t# Direction, offset, waypoint index\r\n", door.direction, door.side_lateral_offset, door.waypoint_index);
+    let _ = write!(out, "\t{} \t# Number of links\r\n", door.num_links);
+    for link in door.door_links.iter() {
+        let _ = write!(out, "\t{} {} {} \t# Distance, door index, teki flag\r\n", format_float(link.distance), link.door_id, link.tekiflag as u8);
+    }
+}
+
+/// Everything before the first FloorInfo section.
+fn caveinfo_header(num_floors: u32) -> String {
+    let mut out = String::new();
+    out.push_str("# CaveInfo\r\n{\r\n");
+    tag_line(&mut out, "c000", &num_floors.to_string(), "Number of floors");
+    out.push_str("\t{_eof}\r\n}\r\n");
+    // The parser skips this line; the game files repeat the floor count here.
+    let _ = write!(out, "{}\r\n", num_floors);
+    out
+}
+
+/// The FloorInfo, TekiInfo, ItemInfo, GateInfo and CapInfo sections for a floor, each
+/// with the comment line before it.
+fn floor_sections(floor: &FloorInfo) -> Result<[String; 5], CaveInfoError> {
+    let mut floorinfo = String::new();
+    floorinfo.push_str("# FloorInfo\r\n{\r\n");
+    tag_line(&mut floorinfo, "f000", &floor.sublevel.to_string(), "Floor start");
+    tag_line(&mut floorinfo, "f001", &floor.sublevel_end.to_string(), "Floor end");
+    tag_line(&mut floorinfo, "f002", &floor.max_main_objects.to_string(), "Max main objects");
+    tag_line(&mut floorinfo, "f003", &floor.max_treasures.to_string(), "Max treasures");
+    tag_line(&mut floorinfo, "f004", &floor.max_gates.to_string(), "Max gates");
+    tag_line(&mut floorinfo, "f014", &format_percentage(floor.cap_probability), "Cap probability (%)");
+    tag_line(&mut floorinfo, "f005", &floor.num_rooms.to_string(), "Number of rooms");
+    tag_line(&mut floorinfo, "f006", &format_float(floor.corridor_probability), "Corridor probability");
+    tag_line(&mut floorinfo, "f007", &(floor.has_geyser as u8).to_string(), "Geyser");
+    tag_line(&mut floorinfo, "f008", &floor.unit_definition_file, "Unit definition file");
+    tag_line(&mut floorinfo, "f009", &floor.lighting_file, "Lighting file");
+    tag_line(&mut floorinfo, "f00A", floor.skybox.as_deref().unwrap_or("none"), "Skybox");
+    tag_line(&mut floorinfo, "f010", &(floor.exit_plugged as u8).to_string(), "Exit plugged");
+    if let Some(echo) = floor.echo {
+        tag_line(&mut floorinfo, "f011", &echo.to_string(), "Echo");
+    }
+    if let Some(music_type) = floor.music_type {
+        tag_line(&mut floorinfo, "f012", &music_type.to_string(), "Music type");
+    }
+    if let Some(hidden_floor) = floor.hidden_floor {
+        tag_line(&mut floorinfo, "f013", &(hidden_floor as u8).to_string(), "Hidden floor");
+    }
+    if let Some(waterwraith_timer) = floor.waterwraith_timer {
+        tag_line(&mut floorinfo, "f015", &format_float(waterwraith_timer), "Waterwraith timer");
+    }
+    if let Some(version) = floor.version {
+        tag_line(&mut floorinfo, "f016", &version.to_string(), "Version");
+    }
+    for (tag, value) in floor.other_tags.iter() {
+        tag_line(&mut floorinfo, &format!("f{}", tag), value, "Unrecognized tag");
+    }
+    floorinfo.push_str("\t{_eof}\r\n}\r\n");
+
+    let mut tekiinfo = String::new();
+    tekiinfo.push_str("# TekiInfo\r\n{\r\n");
+    count_line(&mut tekiinfo, floor.teki_info.len());
+    for teki in floor.teki_info.iter() {
+        write_teki(&mut tekiinfo, teki)?;
+    }
+    tekiinfo.push_str("}\r\n");
+
+    let mut iteminfo = String::new();
+    iteminfo.push_str("# ItemInfo\r\n{\r\n");
+    count_line(&mut iteminfo, floor.item_info.len());
+    for item in floor.item_info.iter() {
+        write_item(&mut iteminfo, item)?;
+    }
+    iteminfo.push_str("}\r\n");
+
+    let mut gateinfo = String::new();
+    gateinfo.push_str("# GateInfo\r\n{\r\n");
+    count_line(&mut gateinfo, floor.gate_info.len());
+    for gate in floor.gate_info.iter() {
+        write_gate(&mut gateinfo, gate);
+    }
+    gateinfo.push_str("}\r\n");
+
+    let mut capinfo = String::new();
+    capinfo.push_str("# CapInfo\r\n{\r\n");
+    count_line(&mut capinfo, floor.cap_info.len());
+    for cap in floor.cap_info.iter() {
+        write_cap(&mut capinfo, cap)?;
+    }
+    capinfo.push_str("}\r\n");
+
+    Ok([floorinfo, tekiinfo, iteminfo, gateinfo, capinfo])
+}
+
+fn write_teki(out: &mut String, teki: &TekiInfo) -> Result<(), CaveInfoError> {
+    let identifier = combined_identifier(&teki.internal_name, &teki.carrying, &teki.spawn_method);
+    // Decorative teki (group 6) store a plain amount with no weight digit.
+    let amount_code = if teki.group == 6 {
+        teki.minimum_amount.to_string()
+    } else {
+        amount_code(&teki.internal_name, teki.minimum_amount, teki.filler_distribution_weight)?
+    };
+    let _ = write!(out, "\t{} \t{} \t# Teki, minimum amount and weight\r\n", identifier, amount_code);
+    let _ = write!(out, "\t{} \t# Group\r\n", teki.group);
+    Ok(())
+}
+
+fn write_item(out: &mut String, item: &ItemInfo) -> Result<(), CaveInfoError> {
+    let amount_code = amount_code(&item.internal_name, item.min_amount as u32, item.filler_distribution_weight)?;
+    let _ = write!(out, "\t{} \t{} \t# Treasure, minimum amount and weight\r\n", item.internal_name, amount_code);
+    Ok(())
+}
+
+fn write_gate(out: &mut String, gate: &GateInfo) {
+    let _ = write!(out, "\tgate \t{} \t# HP\r\n", format_float(gate.health));
+    let _ = write!(out, "\t{} \t# Weight\r\n", gate.spawn_distribution_weight);
+}
+
+fn write_cap(out: &mut String, cap: &CapInfo) -> Result<(), CaveInfoError> {
+    let identifier = combined_identifier(&cap.internal_name, &cap.carrying, &cap.spawn_method);
+    let amount_code = amount_code(&cap.internal_name, cap.minimum_amount, cap.filler_distribution_weight)?;
+    let _ = write!(out, "\t{} \t# Cap type\r\n", cap.cap_type);
+    let _ = write!(out, "\t{} \t{} \t# Teki, minimum amount and weight\r\n", identifier, amount_code);
+    let _ = write!(out, "\t{} \t# Group\r\n", cap.group);
+    Ok(())
+}
+
+/// `$N` spawn method prefix, internal name, and `_treasure` suffix combined into the
+/// single identifier used by TekiInfo and CapInfo, e.g. `$1Kochappy_ahiru`.
+fn combined_identifier(internal_name: &str, carrying: &Option<String>, spawn_method: &Option<String>) -> String {
+    let mut identifier = String::new();
+    if let Some(spawn_method) = spawn_method {
+        identifier.push('$');
+        identifier.push_str(spawn_method);
+    }
+    identifier.push_str(internal_name);
+    if let Some(carrying) = carrying {
+        identifier.push('_');
+        identifier.push_str(carrying);
+    }
+    identifier
+}
+
+/// Minimum amount followed by the single weight digit, with the minimum left out when
+/// it's zero, e.g. min 1 weight 3 is `13` and min 0 weight 5 is `5`.
+fn amount_code(internal_name: &str, minimum_amount: u32, weight: u32) -> Result<String, CaveInfoError> {
+    if weight >= 10 {
+        return Err(CaveInfoError::WriteError(format!("{} has a weight of {}, but weights have to be a single digit", internal_name, weight)));
+    }
+    if minimum_amount == 0 {
+        Ok(weight.to_string())
+    } else {
+        Ok(format!("{}{}", minimum_amount, weight))
+    }
+}
+
+fn tag_line(out: &mut String, tag: &str, value: &str, comment: &str) {
+    // Numbers are 4 bytes in the game's parameter format; -1 marks a string.
+    let size = if value.parse::<f64>().is_ok() { "4" } else { "-1" };
+    let _ = write!(out, "\t{{{}}} {} {} \t# {}\r\n", tag, size, value, comment);
+}
+
+fn count_line(out: &mut String, count: usize) {
+    let _ = write!(out, "\t{} \t# Count\r\n", count);
+}
+
+/// Floats are written with six decimal places like in the game's files, unless that
+/// would lose precision.
+fn format_float(value: f32) -> String {
+    let fixed = format!("{:.6}", value);
+    if fixed.parse::<f32>() == Ok(value) { fixed } else { value.to_string() }
+}
+
+/// The cap probability is stored as a fraction but written as a percentage. Vanilla
+/// files only use whole percentages.
+fn format_percentage(fraction: f32) -> String {
+    let whole = (fraction * 100.0).round();
+    if whole / 100.0 == fraction { format!("{}", whole as u32) } else { (fraction * 100.0).to_string() }
+}
@@ -0,0 +1,294 @@
+/// Generation for every sublevel of a cave at once, for planning and comparing full
+/// cave runs rather than individual sublevels.
+
+use std::{collections::HashMap, rc::Rc};
+use serde::Serialize;
+
+use crate::caveinfo::{display_name, vanilla_caveinfo, CaveInfo, CaveInfoError};
+use super::{Layout, SpawnObject};
+
+/// The assumptions behind the time estimates in [SublevelSummary]. These are round
+/// numbers chosen for planning, not values read from the game, so the estimates are
+/// only useful for comparing layouts against each other.
+///
+/// A sublevel's estimate is the time to walk from the ship to every treasure and carry
+/// it back, plus a fixed cost for each treasure and each gate, plus the time to walk
+/// from the ship to the nearest exit:
+///
+/// ```text
+/// exit_distance / walk_speed
+///     + treasure_distance / walk_speed + treasure_distance / carry_speed
+///     + treasures * seconds_per_treasure
+///     + gates * seconds_per_gate
+/// ```
+///
+/// Every gate in the sublevel is counted, whether or not it's in the way.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TimeModel {
+    /// Captain walking speed in world units per second.
+    pub walk_speed: f32,
+    /// Speed of Pikmin carrying a treasure back to the ship, in world units per second.
+    pub carry_speed: f32,
+    /// Seconds spent on each treasure apart from walking and carrying, e.g. to dig it up
+    /// or gather enough Pikmin to lift it.
+    pub seconds_per_treasure: f32,
+    /// Seconds to break down one gate.
+    pub seconds_per_gate: f32,
+}
+
+impl Default for TimeModel {
+    fn default() -> TimeModel {
+        TimeModel {
+            walk_speed: 150.0,
+            carry_speed: 90.0,
+            seconds_per_treasure: 10.0,
+            seconds_per_gate: 20.0,
+        }
+    }
+}
+
+impl TimeModel {
+    pub fn estimate_seconds(&self, exit_distance: f32, treasure_distance: f32, treasures: usize, gates: usize) -> f32 {
+        exit_distance / self.walk_speed
+            + treasure_distance / self.walk_speed
+            + treasure_distance / self.carry_speed
+            + treasures as f32 * self.seconds_per_treasure
+            + gates as f32 * self.seconds_per_gate
+    }
+}
+
+/// How the seed for each sublevel of a cave is chosen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedRule {
+    /// Every sublevel uses the same seed.
+    Same(u32),
+    /// The first sublevel uses the given seed and each following sublevel uses the
+    /// previous one's seed plus one.
+    Sequential(u32),
+    /// One seed per sublevel, in order. If there are fewer seeds than sublevels, the
+    /// remaining sublevels continue sequentially from the last seed given.
+    PerSublevel(Vec<u32>),
+}
+
+impl SeedRule {
+    /// The seed to use for the sublevel at this (0-based) index.
+    pub fn seed_for(&self, sublevel_index: usize) -> u32 {
+        match self {
+            SeedRule::Same(seed) => *seed,
+            SeedRule::Sequential(seed) => seed.wrapping_add(sublevel_index as u32),
+            SeedRule::PerSublevel(seeds) => match seeds.get(sublevel_index) {
+                Some(seed) => *seed,
+                None => {
+                    let last = seeds.last().copied().unwrap_or_default();
+                    last.wrapping_add((sublevel_index + 1 - seeds.len()) as u32)
+                },
+            },
+        }
+    }
+}
+
+
+/// A generated layout for every sublevel in a cave, in order.
+#[derive(Debug, Clone)]
+pub struct CaveLayout {
+    pub cave_name: String,
+    pub sublevels: Vec<Layout>,
+}
+
+impl CaveLayout {
+    /// Generates every sublevel of the vanilla cave with the given name, e.g. "SH" or
+    /// "Snagret Hole".
+    pub fn generate(cave_name: &str, seeds: &SeedRule) -> Result<CaveLayout, CaveInfoError> {
+        let caveinfo = vanilla_caveinfo(cave_name)?;
+        if caveinfo.floors.is_empty() {
+            return Err(CaveInfoError::InvalidCave(cave_name.to_string()));
+        }
+        Ok(CaveLayout::from_caveinfo(caveinfo, seeds))
+    }
+
+    /// Generates every sublevel of an already loaded cave, e.g. one from another
+    /// region's assets or a modded folder.
+    pub fn from_caveinfo(caveinfo: &CaveInfo, seeds: &SeedRule) -> CaveLayout {
+        let sublevels: Vec<Layout> = caveinfo.floors.iter()
+            .enumerate()
+            .map(|(i, floor)| Layout::generate(seeds.seed_for(i), floor))
+            .collect();
+        let cave_name = caveinfo.floors.first()
+            .and_then(|floor| floor.cave_name.clone())
+            .unwrap_or_default();
+        CaveLayout { cave_name, sublevels }
+    }
+
+    /// Summarizes every sublevel, estimating times with the default [TimeModel].
+    pub fn summary(&self) -> CaveSummary {
+        self.summary_with(&TimeModel::default())
+    }
+
+    pub fn summary_with(&self, time_model: &TimeModel) -> CaveSummary {
+        let sublevels: Vec<SublevelSummary> = self.sublevels.iter().map(|layout| SublevelSummary::new(layout, time_model)).collect();
+        CaveSummary {
+            cave_name: self.cave_name.clone(),
+            total_treasures: sublevels.iter().map(|sublevel| sublevel.treasures.len()).sum(),
+            total_exit_distance: sublevels.iter().map(|sublevel| sublevel.exit_distance).sum(),
+            total_treasure_distance: sublevels.iter().map(|sublevel| sublevel.treasure_distance).sum(),
+            total_estimated_seconds: sublevels.iter().map(|sublevel| sublevel.estimated_seconds).sum(),
+            sublevels,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaveSummary {
+    pub cave_name: String,
+    pub sublevels: Vec<SublevelSummary>,
+    pub total_treasures: usize,
+    pub total_exit_distance: f32,
+    pub total_treasure_distance: f32,
+    pub total_estimated_seconds: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SublevelSummary {
+    pub sublevel: String,
+    pub seed: String,
+    /// Treasures in the sublevel, including ones carried by teki.
+    pub treasures: Vec<String>,
+    /// Display names of the treasures above, in the same order.
+    pub treasure_names: Vec<String>,
+    /// Walking distance from the ship to the exit hole or geyser, in world units.
+    pub exit_distance: f32,
+    /// Total distance to carry every treasure back to the ship, in world units.
+    pub treasure_distance: f32,
+    pub gates: usize,
+    /// Rough time to collect every treasure and reach the exit. See [TimeModel].
+    pub estimated_seconds: f32,
+}
+
+impl SublevelSummary {
+    fn new(layout: &Layout, time_model: &TimeModel) -> SublevelSummary {
+        let door_graph = DoorGraph::new(layout);
+        let mut treasures = Vec::new();
+        let mut start = None;
+        let mut exits = Vec::new();
+        let mut treasure_locations = Vec::new();
+        for (unit_idx, map_unit) in layout.map_units.iter().enumerate() {
+            for spawn_point in map_unit.spawnpoints.iter() {
+                let location = (unit_idx, spawn_point.x, spawn_point.z);
+                let carried_treasures = match spawn_point.contains.as_ref() {
+                    Some(SpawnObject::Ship) => { start = Some(location); Vec::new() },
+                    Some(SpawnObject::Hole(_) | SpawnObject::Geyser) => { exits.push(location); Vec::new() },
+                    Some(SpawnObject::Item(iteminfo)) => vec![iteminfo.internal_name.clone()],
+                    Some(SpawnObject::Teki(tekiinfo)) => tekiinfo.carrying.iter().cloned().collect(),
+                    Some(SpawnObject::TekiBunch(teki_list)) => teki_list.iter().filter_map(|(tekiinfo, _)| tekiinfo.carrying.clone()).collect(),
+                    Some(SpawnObject::CapTeki(capinfo, _)) => capinfo.carrying.iter().cloned().collect(),
+                    _ => Vec::new(),
+                };
+                for treasure in carried_treasures {
+                    treasures.push(treasure);
+                    treasure_locations.push(location);
+                }
+            }
+        }
+
+        let (exit_distance, treasure_distance) = match start {
+            Some(start) => {
+                let distances = door_graph.distances_from(start);
+                let distance_to = |location| door_graph.distance_to(&distances, start, location);
+                (
+                    exits.iter().map(|exit| distance_to(*exit)).fold(f32::INFINITY, f32::min),
+                    treasure_locations.iter().map(|treasure| distance_to(*treasure)).sum(),
+                )
+            },
+            None => (0.0, 0.0),
+        };
+        let exit_distance = if exit_distance.is_finite() { exit_distance } else { 0.0 };
+        let gates = layout.map_units.iter()
+            .flat_map(|map_unit| map_unit.doors.iter())
+            .filter(|door| matches!(door.borrow().seam_spawnpoint, Some(SpawnObject::Gate(_))))
+            .count();
+        let estimated_seconds = time_model.estimate_seconds(exit_distance, treasure_distance, treasures.len(), gates);
+
+        SublevelSummary {
+            sublevel: layout.cave_name.clone(),
+            seed: format!("{:#010X}", layout.starting_seed),
+            treasure_names: treasures.iter().map(|treasure| display_name(treasure)).collect(),
+            treasures,
+            exit_distance,
+            treasure_distance,
+            gates,
+            estimated_seconds,
+        }
+    }
+}
+
+
+/// Distances between points in a layout, following doors between map units. Within a
+/// single unit, distances are straight lines, so these underestimate the true walking
+/// distance in rooms with obstacles.
+struct DoorGraph {
+    // (parent unit index, x, z) for each door
+    doors: Vec<(usize, f32, f32)>,
+    // Index of the door on the other side of each door, if it's connected.
+    adjacent: Vec<Option<usize>>,
+}
+
+impl DoorGraph {
+    fn new(layout: &Layout) -> DoorGraph {
+        let mut door_indices = HashMap::new();
+        let mut doors = Vec::new();
+        for (unit_idx, map_unit) in layout.map_units.iter().enumerate() {
+            for door in map_unit.doors.iter() {
+                door_indices.insert(Rc::as_ptr(door), doors.len());
+                let (x, z) = door.borrow().world_position();
+                doors.push((unit_idx, x, z));
+            }
+        }
+        let adjacent = layout.map_units.iter()
+            .flat_map(|map_unit| map_unit.doors.iter())
+            .map(|door| {
+                door.borrow().adjacent_door.as_ref()
+                    .and_then(|adjacent| adjacent.upgrade())
+                    .and_then(|adjacent| door_indices.get(&Rc::as_ptr(&adjacent)).copied())
+            })
+            .collect();
+        DoorGraph { doors, adjacent }
+    }
+
+    /// Shortest distance from the start point to every door (Dijkstra's algorithm).
+    fn distances_from(&self, (start_unit, start_x, start_z): (usize, f32, f32)) -> Vec<f32> {
+        let mut distances: Vec<f32> = self.doors.iter()
+            .map(|(unit, x, z)| if *unit == start_unit { dist(start_x, start_z, *x, *z) } else { f32::INFINITY })
+            .collect();
+        let mut visited = vec![false; self.doors.len()];
+        while let Some(current) = (0..self.doors.len())
+            .filter(|i| !visited[*i] && distances[*i].is_finite())
+            .min_by(|a, b| distances[*a].total_cmp(&distances[*b]))
+        {
+            visited[current] = true;
+            let (unit, x, z) = self.doors[current];
+            if let Some(adjacent) = self.adjacent[current] {
+                distances[adjacent] = distances[adjacent].min(distances[current]);
+            }
+            for (other, (other_unit, other_x, other_z)) in self.doors.iter().enumerate() {
+                if *other_unit == unit {
+                    distances[other] = distances[other].min(distances[current] + dist(x, z, *other_x, *other_z));
+                }
+            }
+        }
+        distances
+    }
+
+    fn distance_to(&self, door_distances: &[f32], (start_unit, start_x, start_z): (usize, f32, f32), (unit, x, z): (usize, f32, f32)) -> f32 {
+        let direct = if unit == start_unit { dist(start_x, start_z, x, z) } else { f32::INFINITY };
+        self.doors.iter()
+            .zip(door_distances.iter())
+            .filter(|((door_unit, _, _), _)| *door_unit == unit)
+            .map(|((_, door_x, door_z), door_distance)| door_distance + dist(*door_x, *door_z, x, z))
+            .fold(direct, f32::min)
+    }
+}
+
+fn dist(x1: f32, z1: f32, x2: f32, z2: f32) -> f32 {
+    ((x1 - x2).powi(2) + (z1 - z2).powi(2)).sqrt()
+}
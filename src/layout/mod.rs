@@ -1,12 +1,15 @@
+pub mod cave;
 pub mod render;
+pub mod render_ascii;
 #[cfg(test)]
 pub mod test;
 
 use std::{cell::RefCell, cmp::{max, min}, rc::{Rc, Weak}};
 use itertools::Itertools;
 use log::debug;
+use serde::{Serialize, Serializer, ser::SerializeStruct};
 
-use crate::{caveinfo::{CapInfo, CaveUnit, DoorUnit, FloorInfo, GateInfo, ItemInfo, RoomType, SpawnPoint, TekiInfo}, pikmin_math::{PikminRng, self}};
+use crate::{caveinfo::{display_name, CapInfo, CaveUnit, DoorUnit, FloorInfo, GateInfo, ItemInfo, RoomType, SpawnPoint, TekiInfo}, pikmin_math::{PikminRng, self}};
 
 /// Represents a generated sublevel layout.
 /// Given a seed and a CaveInfo file, a layout can be generated using a
@@ -146,13 +149,7 @@ impl Layout {
             }
 
             for door in map_unit.doors.iter() {
-                let mut x = (door.borrow().x * 170) as f32;
-                let mut z = (door.borrow().z * 170) as f32;
-                match door.borrow().door_unit.direction {
-                    0 | 2 => x += 85.0,
-                    1 | 3 => z += 85.0,
-                    _ => panic!("Invalid door direction in slug"),
-                }
+                let (x, z) = door.borrow().world_position();
                 match &door.borrow().seam_spawnpoint {
                     Some(SpawnObject::Teki(tekiinfo)) => {
                         spawn_object_slugs.push(format!("{},carrying:{},spawn_method:{},x{}z{};",
@@ -181,6 +178,92 @@ impl Layout {
 
         slug
     }
+
+    /// The bounding box of all placed map units in map tile coordinates, as
+    /// (min_x, min_z, max_x, max_z).
+    pub fn map_bounds(&self) -> (isize, isize, isize, isize) {
+        let min_x = self.map_units.iter().map(|unit| unit.x).min().unwrap();
+        let max_x = self.map_units.iter().map(|unit| unit.x + unit.unit.width as isize).max().unwrap();
+        let min_z = self.map_units.iter().map(|unit| unit.z).min().unwrap();
+        let max_z = self.map_units.iter().map(|unit| unit.z + unit.unit.height as isize).max().unwrap();
+        (min_x, min_z, max_x, max_z)
+    }
+
+    /// Every object placed in this layout along with its world coordinates. Teki bunches
+    /// are split up into their individual teki, and falling cap teki and objects in door
+    /// seams are included as well.
+    pub fn spawn_objects(&self) -> Vec<(SpawnObject, f32, f32)> {
+        let mut spawn_objects = Vec::new();
+        for spawn_point in self.map_units.iter().flat_map(|unit| unit.spawnpoints.iter()) {
+            match spawn_point.contains.as_ref() {
+                Some(SpawnObject::TekiBunch(teki_list)) => {
+                    for (tekiinfo, (dx, _, dz)) in teki_list.iter() {
+                        spawn_objects.push((SpawnObject::Teki(tekiinfo.clone()), spawn_point.x + dx, spawn_point.z + dz));
+                    }
+                },
+                Some(SpawnObject::TekiDuplicate) | None => {},
+                Some(spawn_object) => spawn_objects.push((spawn_object.clone(), spawn_point.x, spawn_point.z)),
+            }
+            if let Some(spawn_object) = spawn_point.falling_cap_teki.as_ref() {
+                spawn_objects.push((spawn_object.clone(), spawn_point.x, spawn_point.z));
+            }
+        }
+        for door in self.map_units.iter().flat_map(|unit| unit.doors.iter()) {
+            let (x, z) = door.borrow().world_position();
+            if let Some(spawn_object) = door.borrow().seam_spawnpoint.as_ref() {
+                spawn_objects.push((spawn_object.clone(), x, z));
+            }
+        }
+        spawn_objects
+    }
+}
+
+/// Layouts are serialized as a flat list of map units and spawned objects rather than
+/// field-by-field, since most of the internal state (door links, scores, etc.) is only
+/// meaningful during generation.
+impl Serialize for Layout {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct MapUnitEntry<'a> {
+            name: &'a str,
+            x: isize,
+            z: isize,
+            rotation: u16,
+            room_type: &'a RoomType,
+        }
+
+        #[derive(Serialize)]
+        struct SpawnObjectEntry {
+            object: SpawnObject,
+            name: String,
+            carrying_name: Option<String>,
+            x: f32,
+            z: f32,
+        }
+
+        let map_units: Vec<MapUnitEntry> = self.map_units.iter()
+            .map(|map_unit| MapUnitEntry {
+                name: &map_unit.unit.unit_folder_name,
+                x: map_unit.x,
+                z: map_unit.z,
+                rotation: map_unit.unit.rotation,
+                room_type: &map_unit.unit.room_type,
+            })
+            .collect();
+        let spawn_objects: Vec<SpawnObjectEntry> = self.spawn_objects().into_iter()
+            .map(|(object, x, z)| {
+                let (name, carrying_name) = object.display_names();
+                SpawnObjectEntry { object, name, carrying_name, x, z }
+            })
+            .collect();
+
+        let mut state = serializer.serialize_struct("Layout", 4)?;
+        state.serialize_field("sublevel", &self.cave_name)?;
+        state.serialize_field("seed", &format!("{:#010X}", self.starting_seed))?;
+        state.serialize_field("map_units", &map_units)?;
+        state.serialize_field("spawn_objects", &spawn_objects)?;
+        state.end()
+    }
 }
 
 struct LayoutBuilder {
@@ -2014,6 +2097,19 @@ impl PlacedDoor {
     pub fn lines_up_with(&self, other: &PlacedDoor) -> bool {
         self.facing(other) && self.x == other.x && self.z == other.z
     }
+
+    /// World coordinates of the center of this door, which is where seam teki and
+    /// gates are placed.
+    pub fn world_position(&self) -> (f32, f32) {
+        let mut x = (self.x * 170) as f32;
+        let mut z = (self.z * 170) as f32;
+        match self.door_unit.direction {
+            0 | 2 => x += 85.0,
+            1 | 3 => z += 85.0,
+            _ => panic!("Invalid door direction"),
+        }
+        (x, z)
+    }
 }
 
 pub fn boxes_overlap(x1: isize, z1: isize, w1: u16, h1: u16, x2: isize, z2: isize, w2: u16, h2: u16) -> bool {
@@ -2042,7 +2138,7 @@ fn spawn_point_dist(a: &PlacedSpawnPoint, b: &PlacedSpawnPoint) -> f32 {
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum SpawnObject {
     Teki(TekiInfo),
     TekiBunch(Vec<(TekiInfo, (f32, f32, f32))>), // For group 0 enemies. Tuple is displacement from parent spawnpoint.
@@ -2054,4 +2150,26 @@ pub enum SpawnObject {
     Hole(bool), // Plugged or not
     Geyser,
     Ship
+}
+
+impl SpawnObject {
+    /// Human-readable name of the object, e.g. "Dwarf Red Bulborb", and of the treasure
+    /// it's carrying if there is one. Teki bunches are named after their first teki.
+    pub fn display_names(&self) -> (String, Option<String>) {
+        let named = |internal_name: &str, carrying: &Option<String>| (display_name(internal_name), carrying.as_deref().map(display_name));
+        match self {
+            SpawnObject::Teki(tekiinfo) | SpawnObject::PlantTeki(tekiinfo) => named(&tekiinfo.internal_name, &tekiinfo.carrying),
+            SpawnObject::TekiBunch(teki_list) => teki_list.first()
+                .map(|(tekiinfo, _)| named(&tekiinfo.internal_name, &tekiinfo.carrying))
+                .unwrap_or_default(),
+            SpawnObject::CapTeki(capinfo, _) => named(&capinfo.internal_name, &capinfo.carrying),
+            SpawnObject::Item(iteminfo) => (display_name(&iteminfo.internal_name), None),
+            SpawnObject::Gate(_) => ("Gate".to_owned(), None),
+            SpawnObject::Hole(true) => ("Exit Hole (plugged)".to_owned(), None),
+            SpawnObject::Hole(false) => ("Exit Hole".to_owned(), None),
+            SpawnObject::Geyser => ("Geyser".to_owned(), None),
+            SpawnObject::Ship => ("Research Pod".to_owned(), None),
+            SpawnObject::TekiDuplicate => (String::new(), None),
+        }
+    }
 }
\ No newline at end of file
@@ -1,25 +1,80 @@
+use std::borrow::Cow;
 use std::error::Error;
-
-use crate::assets::get_file_bytes;
-use crate::caveinfo::{CapInfo, GateInfo, ItemInfo, TekiInfo, get_resource_file_bytes, get_special_texture_name};
-use super::{Layout, SpawnObject, PlacedMapUnit};
-use image::RgbImage;
-use image::{ImageFormat, DynamicImage, GenericImage, GenericImageView, Pixel, imageops::FilterType};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use cached::proc_macro::cached;
+use crate::assets::{get_file_bytes, treasure_texture_folder, Region};
+use crate::caveinfo::{CapInfo, CaveInfoError, CaveUnit, FloorInfo, GateInfo, ItemInfo, TekiInfo, display_name, get_resource_file_bytes, get_special_texture_name, get_custom_texture_path};
+use super::{Layout, SpawnObject, PlacedMapUnit, cave::CaveLayout};
+use fontdue::{Font, FontSettings};
+use itertools::Itertools;
+use image::{RgbImage, RgbaImage, Rgba, ImageResult};
+use image::{DynamicImage, GenericImage, GenericImageView, Pixel, imageops::FilterType};
 use log::debug;
+use once_cell::sync::Lazy;
 
 const RENDER_SCALE: u32 = 8;
 const GATE_SCALE: f32 = 1.7;
 const TREASURE_SCALE: f32 = 1.1;
 
+// Caveinfo overview card layout
+const CARD_WIDTH: u32 = 1400;
+const CARD_MARGIN: u32 = 16;
+const CARD_HEADER_HEIGHT: u32 = 90;
+const CARD_UNIT_SCALE: u32 = 4;
+const CARD_ICON_SIZE: u32 = 32;
+const CARD_HEADING_SIZE: f32 = 22.0;
+const CARD_TEXT_SIZE: f32 = 16.0;
+const CARD_LABEL_SIZE: f32 = 12.0;
+
+// Single cave unit images
+const UNIT_RENDER_SCALE: u32 = 6;
+const UNIT_PANEL_PADDING: u32 = 90;
+const UNIT_ARROW_LENGTH: f32 = 24.0;
+
+static FONT: Lazy<Font> = Lazy::new(|| {
+    let font_bytes = get_resource_file_bytes("resources/fonts/DejaVuSansMono-Bold.ttf").unwrap();
+    Font::from_bytes(font_bytes.as_ref(), FontSettings::default()).unwrap()
+});
+
+
+/// A finished render. Rendering itself never fails; anything that couldn't be drawn
+/// properly is replaced with a placeholder and reported in `warnings` instead.
+pub struct RenderResult {
+    pub image: DynamicImage,
+    pub warnings: Vec<RenderWarning>,
+}
+
+impl RenderResult {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.image.save_with_format(path, image::ImageFormat::Png)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderWarning {
+    /// No texture could be found for the object with this internal name. `path` is
+    /// the file that was expected to contain it.
+    MissingTexture { name: String, path: String },
+}
 
-pub fn render_layout(layout: &Layout) {
+impl Display for RenderWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderWarning::MissingTexture { name, path } => write!(f, "Missing texture for '{}' (expected at '{}')", name, path),
+        }
+    }
+}
+
+
+/// Textures are read from the given region's assets.
+pub fn render_layout(layout: &Layout, region: Region) -> RenderResult {
     debug!("Generating layout image.");
+    let mut warnings = Vec::new();
 
     // Find the minimum and maximum map tile coordinates in the layout.
-    let min_map_x = layout.map_units.iter().map(|unit| unit.x).min().unwrap();
-    let max_map_x = layout.map_units.iter().map(|unit| unit.x + unit.unit.width as isize).max().unwrap();
-    let min_map_z = layout.map_units.iter().map(|unit| unit.z).min().unwrap();
-    let max_map_z = layout.map_units.iter().map(|unit| unit.z + unit.unit.height as isize).max().unwrap();
+    let (min_map_x, min_map_z, max_map_x, max_map_z) = layout.map_bounds();
 
     // Each map tile is 8x8 pixels on the radar.
     // We scale this up further so teki and treasure textures can be rendered at a decent
@@ -31,15 +86,20 @@ pub fn render_layout(layout: &Layout) {
 
     // Draw map units
     for map_unit in layout.map_units.iter() {
-        let mut radar_image = map_unit.get_texture();
-        
-        for _ in 0..map_unit.unit.rotation {
-            radar_image = radar_image.rotate90();
-        }
+        let radar_image = match map_unit.get_texture(region, None) {
+            Ok(mut radar_image) => {
+                for _ in 0..map_unit.unit.rotation {
+                    radar_image = radar_image.rotate90();
+                }
+                radar_image
+            },
+            // The placeholder is created at the rotated size already, so it doesn't need rotating.
+            Err(warning) => placeholder_for(warning, map_unit.unit.width as u32 * 8, map_unit.unit.height as u32 * 8, &mut warnings),
+        };
 
         let radar_image = radar_image.resize(
-            radar_image.width() * RENDER_SCALE, 
-            radar_image.height() * RENDER_SCALE, 
+            radar_image.width() * RENDER_SCALE,
+            radar_image.height() * RENDER_SCALE,
             FilterType::Nearest
         );
 
@@ -59,21 +119,21 @@ pub fn render_layout(layout: &Layout) {
             match spawn_object {
                 SpawnObject::TekiBunch(teki_list) => {
                     for (tekiinfo, (dx, _, dz)) in teki_list.iter() {
-                        draw_object_at(&mut image_buffer, tekiinfo, spawn_point.x + dx, spawn_point.z + dz, min_map_x, min_map_z, 1.0);
+                        draw_object_at(&mut image_buffer, tekiinfo, spawn_point.x + dx, spawn_point.z + dz, min_map_x, min_map_z, 1.0, region, &mut warnings);
                     }
                 },
                 SpawnObject::Item(iteminfo) => {
-                    draw_object_at(&mut image_buffer, iteminfo, spawn_point.x, spawn_point.z, min_map_x, min_map_z, TREASURE_SCALE);
+                    draw_object_at(&mut image_buffer, iteminfo, spawn_point.x, spawn_point.z, min_map_x, min_map_z, TREASURE_SCALE, region, &mut warnings);
                 },
                 _ => {
-                    draw_object_at(&mut image_buffer, spawn_object, spawn_point.x, spawn_point.z, min_map_x, min_map_z, 1.0);
+                    draw_object_at(&mut image_buffer, spawn_object, spawn_point.x, spawn_point.z, min_map_x, min_map_z, 1.0, region, &mut warnings);
                 },
             }
         }
-        
+
         // Draw falling cap teki
         if let Some(spawn_object) = spawn_point.falling_cap_teki.as_ref() {
-            draw_object_at(&mut image_buffer, spawn_object, spawn_point.x - 30.0, spawn_point.z - 30.0, min_map_x, min_map_z, 1.0);
+            draw_object_at(&mut image_buffer, spawn_object, spawn_point.x - 30.0, spawn_point.z - 30.0, min_map_x, min_map_z, 1.0, region, &mut warnings);
         }
     }
 
@@ -81,64 +141,363 @@ pub fn render_layout(layout: &Layout) {
     for door in layout.map_units.iter().flat_map(|unit| unit.doors.iter()) {
         if let Some(spawn_object) = door.borrow().seam_spawnpoint.as_ref() {
             // Adjust the door's map tile coordinates to world coordinates
-            let mut x = (door.borrow().x * 170) as f32;
-            let mut z = (door.borrow().z * 170) as f32;
-            match door.borrow().door_unit.direction {
-                0 | 2 => x += 85.0,
-                1 | 3 => z += 85.0,
-                _ => panic!("Invalid door direction in render"),
-            }
+            let (x, z) = door.borrow().world_position();
 
             match spawn_object {
                 SpawnObject::TekiBunch(teki_list) => {
                     for (tekiinfo, (dx, _, dz)) in teki_list.iter() {
-                        draw_object_at(&mut image_buffer, tekiinfo, x + dx, z + dz, min_map_x, min_map_z, 1.0);
+                        draw_object_at(&mut image_buffer, tekiinfo, x + dx, z + dz, min_map_x, min_map_z, 1.0, region, &mut warnings);
                     }
                 },
                 SpawnObject::Gate(gateinfo) => {
-                    let mut texture = gateinfo.get_texture();
-                    if door.borrow().door_unit.direction % 2 == 1 {
-                        texture = texture.rotate90();
-                    }
-                    draw_object_at(&mut image_buffer, &texture, x, z, min_map_x, min_map_z, GATE_SCALE);
+                    let gate = PlacedGate { gate: gateinfo, direction: door.borrow().door_unit.direction };
+                    draw_object_at(&mut image_buffer, &gate, x, z, min_map_x, min_map_z, GATE_SCALE, region, &mut warnings);
                 }
                 _ => {
-                    draw_object_at(&mut image_buffer, spawn_object, x, z, min_map_x, min_map_z, 1.0);
+                    draw_object_at(&mut image_buffer, spawn_object, x, z, min_map_x, min_map_z, 1.0, region, &mut warnings);
                 },
             }
         }
     }
 
-    image_buffer.save_with_format("./caveripper_output/layout.png", image::ImageFormat::Png).unwrap();
+    RenderResult {
+        image: image_buffer,
+        warnings,
+    }
 }
 
-// x and y are world coordinates, not image or map unit coordinates
-fn draw_object_at<Tex: Textured>(image_buffer: &mut DynamicImage, obj: &Tex, x: f32, z: f32, min_map_x: isize, min_map_z: isize, scale: f32) {
-    let texture = obj.get_texture();
-    let texture = texture.resize(
-        (32.0 * scale) as u32, (32.0 * scale) as u32,
-        FilterType::Lanczos3
+/// Renders an overview card for a sublevel, independent of any particular seed. This
+/// shows every map unit that can be used along with its doors and spawn points, plus
+/// every teki, treasure, cap teki and gate that can spawn and the parameters they
+/// spawn with.
+pub fn render_caveinfo(floor: &FloorInfo, region: Region) -> RenderResult {
+    debug!("Generating caveinfo image.");
+    let mut warnings = Vec::new();
+    let cell_size = 8 * CARD_UNIT_SCALE;
+
+    // Only rotation 0 of each unit is shown since the others are just rotated copies.
+    // Units are laid out in rows that wrap at the card's width, with a label under each.
+    let units: Vec<&CaveUnit> = floor.cave_units.iter()
+        .filter(|unit| unit.rotation == 0)
+        .unique_by(|unit| &unit.unit_folder_name)
+        .collect();
+    let mut unit_positions = Vec::with_capacity(units.len());
+    let (mut x, mut y, mut row_height) = (CARD_MARGIN, CARD_HEADER_HEIGHT, 0);
+    for unit in units.iter() {
+        let slot_width = (unit.width as u32 * cell_size).max(text_width(&unit.unit_folder_name, CARD_LABEL_SIZE));
+        let slot_height = unit.height as u32 * cell_size + CARD_LABEL_SIZE as u32 + 4;
+        if x + slot_width > CARD_WIDTH - CARD_MARGIN && x > CARD_MARGIN {
+            x = CARD_MARGIN;
+            y += row_height + CARD_MARGIN;
+            row_height = 0;
+        }
+        unit_positions.push((x, y));
+        x += slot_width + CARD_MARGIN;
+        row_height = row_height.max(slot_height);
+    }
+    let units_bottom = y + row_height + CARD_MARGIN;
+
+    let sections: [(&str, Vec<(&dyn Textured, String)>); 4] = [
+        ("Teki", floor.teki_info.iter().map(|teki| (teki as &dyn Textured, describe_teki(teki))).collect()),
+        ("Treasures", floor.item_info.iter().map(|item| {
+            (item as &dyn Textured, format!("{}: min {}, weight {}", display_name(&item.internal_name), item.min_amount, item.filler_distribution_weight))
+        }).collect()),
+        ("Cap Teki", floor.cap_info.iter().map(|cap| (cap as &dyn Textured, describe_cap_teki(cap))).collect()),
+        ("Gates", floor.gate_info.iter().map(|gate| {
+            (gate as &dyn Textured, format!("{} HP: weight {}", gate.health.round(), gate.spawn_distribution_weight))
+        }).collect()),
+    ];
+    let panel_height: u32 = sections.iter()
+        .filter(|(_, entries)| !entries.is_empty())
+        .map(|(_, entries)| CARD_HEADING_SIZE as u32 + 8 + entries.len() as u32 * (CARD_ICON_SIZE + 6) + CARD_MARGIN)
+        .sum();
+
+    let mut canvas = DynamicImage::ImageRgba8(RgbaImage::from_pixel(CARD_WIDTH, units_bottom + panel_height + CARD_MARGIN, Rgba([25, 25, 30, 255])));
+    let white = Rgba([255, 255, 255, 255]);
+    let gray = Rgba([180, 180, 180, 255]);
+
+    // Header with the floor-wide generation parameters and a legend for spawn point colors.
+    let title = match (floor.has_waterwraith(), floor.waterwraith_timer) {
+        (true, Some(timer)) => format!("{} (Waterwraith after {}s)", floor.name(), timer),
+        (true, None) => format!("{} (Waterwraith)", floor.name()),
+        (false, _) => floor.name(),
+    };
+    draw_text(&mut canvas, &title, CARD_MARGIN as i32, 10, CARD_HEADING_SIZE, white);
+    let parameters = format!(
+        "Main objects: {}  Treasures: {}  Gates: {}  Rooms: {}  Corridor prob: {}  Cap prob: {}  Geyser: {}  Plugged exit: {}",
+        floor.max_main_objects, floor.max_treasures, floor.max_gates, floor.num_rooms,
+        floor.corridor_probability, floor.cap_probability,
+        if floor.has_geyser { "yes" } else { "no" }, if floor.exit_plugged { "yes" } else { "no" },
+    );
+    draw_text(&mut canvas, &parameters, CARD_MARGIN as i32, 40, CARD_LABEL_SIZE, gray);
+    let mut legend_x = CARD_MARGIN as i32;
+    for group in [0, 1, 2, 4, 6, 7, 8, 9] {
+        draw_circle(&mut canvas, legend_x as f32 + 5.0, 68.0, 5.0, spawn_group_color(group));
+        let label = format!("{} {}", group, spawn_group_name(group));
+        draw_text(&mut canvas, &label, legend_x + 14, 61, CARD_LABEL_SIZE, gray);
+        legend_x += text_width(&label, CARD_LABEL_SIZE) as i32 + 30;
+    }
+
+    // Map units with their doors and spawn points drawn on top.
+    for (unit, (unit_x, unit_y)) in units.iter().zip(unit_positions.into_iter()) {
+        let placed_unit = PlacedMapUnit::new(unit, 0, 0);
+        let radar_image = match placed_unit.get_texture(region, None) {
+            Ok(radar_image) => radar_image.resize(radar_image.width() * CARD_UNIT_SCALE, radar_image.height() * CARD_UNIT_SCALE, FilterType::Nearest),
+            Err(warning) => placeholder_for(warning, unit.width as u32 * cell_size, unit.height as u32 * cell_size, &mut warnings),
+        };
+        blend(&mut canvas, &radar_image, unit_x as i32, unit_y as i32);
+
+        let to_card_coords = |x: f32, z: f32| (unit_x as f32 + (x / 170.0) * cell_size as f32, unit_y as f32 + (z / 170.0) * cell_size as f32);
+        for door in placed_unit.doors.iter() {
+            let door = door.borrow();
+            let (door_x, door_z) = to_card_coords(door.world_position().0, door.world_position().1);
+            let (bar_width, bar_height) = match door.door_unit.direction {
+                0 | 2 => (cell_size as f32 * 0.6, 6.0),
+                _ => (6.0, cell_size as f32 * 0.6),
+            };
+            fill_rect(&mut canvas, door_x - bar_width / 2.0, door_z - bar_height / 2.0, bar_width, bar_height, Rgba([90, 160, 255, 255]));
+        }
+        for spawn_point in placed_unit.spawnpoints.iter() {
+            let (sp_x, sp_z) = to_card_coords(spawn_point.x, spawn_point.z);
+            let mut color = spawn_group_color(spawn_point.spawnpoint_unit.group);
+            let radius = (spawn_point.spawnpoint_unit.radius / 170.0) * cell_size as f32;
+            if radius > 3.0 {
+                color.0[3] = 70;
+                draw_circle(&mut canvas, sp_x, sp_z, radius, color);
+                color.0[3] = 255;
+            }
+            draw_circle(&mut canvas, sp_x, sp_z, 3.0, color);
+        }
+
+        draw_text(&mut canvas, &unit.unit_folder_name, unit_x as i32, (unit_y + unit.height as u32 * cell_size) as i32 + 2, CARD_LABEL_SIZE, gray);
+    }
+
+    // Spawnable object panel
+    let mut y = units_bottom as i32;
+    for (heading, entries) in sections.iter().filter(|(_, entries)| !entries.is_empty()) {
+        draw_text(&mut canvas, heading, CARD_MARGIN as i32, y, CARD_HEADING_SIZE, white);
+        y += CARD_HEADING_SIZE as i32 + 8;
+        for (obj, description) in entries.iter() {
+            let icon_center = CARD_MARGIN as f32 + CARD_ICON_SIZE as f32 / 2.0;
+            draw_icon_at(&mut canvas, *obj, icon_center, y as f32 + CARD_ICON_SIZE as f32 / 2.0, CARD_ICON_SIZE, region, &mut warnings);
+            let text_y = y + (CARD_ICON_SIZE as i32 - CARD_TEXT_SIZE as i32) / 2;
+            draw_text(&mut canvas, description, (CARD_MARGIN + CARD_ICON_SIZE + 12) as i32, text_y, CARD_TEXT_SIZE, white);
+            y += (CARD_ICON_SIZE + 6) as i32;
+        }
+        y += CARD_MARGIN as i32;
+    }
+
+    RenderResult {
+        image: canvas,
+        warnings,
+    }
+}
+
+/// Renders a single map unit in all four rotations side by side, with each door's index,
+/// direction and side offset and each spawn point's group and radius labelled. This is
+/// mostly useful for checking the unit rotation logic against the game.
+pub fn render_cave_unit(unit: &CaveUnit, region: Region) -> RenderResult {
+    debug!("Generating image for cave unit {}.", unit.unit_folder_name);
+    let mut warnings = Vec::new();
+    let cell_size = 8 * UNIT_RENDER_SCALE;
+    let panel_size = unit.width.max(unit.height) as u32 * cell_size + 2 * UNIT_PANEL_PADDING;
+    let title_height = CARD_HEADING_SIZE as u32 + 2 * CARD_MARGIN;
+    let mut canvas = DynamicImage::ImageRgba8(RgbaImage::from_pixel(panel_size * 4, title_height + CARD_TEXT_SIZE as u32 + panel_size, Rgba([25, 25, 30, 255])));
+    let white = Rgba([255, 255, 255, 255]);
+    let door_color = Rgba([90, 160, 255, 255]);
+
+    let title = format!("{} ({:?}, {} doors)", unit.unit_folder_name, unit.room_type, unit.num_doors);
+    draw_text(&mut canvas, &title, CARD_MARGIN as i32, CARD_MARGIN as i32, CARD_HEADING_SIZE, white);
+
+    for rotation in 0..4 {
+        let panel_x = rotation as u32 * panel_size;
+        draw_text(&mut canvas, &format!("rotation {}", rotation), (panel_x + CARD_MARGIN) as i32, title_height as i32, CARD_TEXT_SIZE, white);
+
+        let placed_unit = PlacedMapUnit::new(&unit.copy_and_rotate_to(rotation), 0, 0);
+        let unit_x = panel_x + UNIT_PANEL_PADDING;
+        let unit_y = title_height + CARD_TEXT_SIZE as u32 + UNIT_PANEL_PADDING;
+        let radar_image = match placed_unit.get_texture(region, None) {
+            Ok(mut radar_image) => {
+                for _ in 0..placed_unit.unit.rotation {
+                    radar_image = radar_image.rotate90();
+                }
+                radar_image.resize(radar_image.width() * UNIT_RENDER_SCALE, radar_image.height() * UNIT_RENDER_SCALE, FilterType::Nearest)
+            },
+            Err(warning) => placeholder_for(warning, placed_unit.unit.width as u32 * cell_size, placed_unit.unit.height as u32 * cell_size, &mut warnings),
+        };
+        blend(&mut canvas, &radar_image, unit_x as i32, unit_y as i32);
+
+        let to_panel_coords = |x: f32, z: f32| (unit_x as f32 + (x / 170.0) * cell_size as f32, unit_y as f32 + (z / 170.0) * cell_size as f32);
+        for (door_index, door) in placed_unit.doors.iter().enumerate() {
+            let door = door.borrow();
+            let (x, z) = door.world_position();
+            let (door_x, door_z) = to_panel_coords(x, z);
+            let direction = door.door_unit.direction;
+            draw_arrow(&mut canvas, door_x, door_z, direction, UNIT_ARROW_LENGTH, door_color);
+
+            // Label goes just past the tip of the arrow.
+            let label = [format!("#{}", door_index), format!("d{} o{}", direction, door.door_unit.side_lateral_offset)];
+            let label_width = label.iter().map(|line| text_width(line, CARD_LABEL_SIZE)).max().unwrap_or_default() as f32;
+            let label_height = CARD_LABEL_SIZE * 2.0;
+            let gap = UNIT_ARROW_LENGTH + 4.0;
+            let (label_x, label_z) = match direction {
+                0 => (door_x - label_width / 2.0, door_z - gap - label_height),
+                1 => (door_x + gap, door_z - label_height / 2.0),
+                2 => (door_x - label_width / 2.0, door_z + gap),
+                _ => (door_x - gap - label_width, door_z - label_height / 2.0),
+            };
+            for (i, line) in label.iter().enumerate() {
+                draw_text(&mut canvas, line, label_x as i32, (label_z + i as f32 * CARD_LABEL_SIZE) as i32, CARD_LABEL_SIZE, door_color);
+            }
+        }
+
+        for spawn_point in placed_unit.spawnpoints.iter() {
+            let (sp_x, sp_z) = to_panel_coords(spawn_point.x, spawn_point.z);
+            let mut color = spawn_group_color(spawn_point.spawnpoint_unit.group);
+            let radius = (spawn_point.spawnpoint_unit.radius / 170.0) * cell_size as f32;
+            if radius > 3.0 {
+                color.0[3] = 70;
+                draw_circle(&mut canvas, sp_x, sp_z, radius, color);
+                color.0[3] = 255;
+            }
+            draw_circle(&mut canvas, sp_x, sp_z, 3.0, color);
+            let label = format!("g{} r{}", spawn_point.spawnpoint_unit.group, spawn_point.spawnpoint_unit.radius);
+            draw_text(&mut canvas, &label, sp_x as i32 + 5, sp_z as i32 - CARD_LABEL_SIZE as i32 / 2, CARD_LABEL_SIZE, color);
+        }
+    }
+
+    RenderResult {
+        image: canvas,
+        warnings,
+    }
+}
+
+fn describe_teki(teki: &TekiInfo) -> String {
+    let mut description = format!(
+        "{} (group {}, {}): min {}, weight {}",
+        display_name(&teki.internal_name), teki.group, spawn_group_name(teki.group as u16), teki.minimum_amount, teki.filler_distribution_weight
     );
+    if let Some(carrying) = teki.carrying.as_ref() {
+        description.push_str(&format!(", carrying {}", display_name(carrying)));
+    }
+    if let Some(spawn_method) = teki.spawn_method.as_ref() {
+        description.push_str(&format!(", spawn method {}", spawn_method));
+    }
+    description
+}
+
+fn describe_cap_teki(cap: &CapInfo) -> String {
+    let mut description = format!(
+        "{} (group {}): min {}, weight {}",
+        display_name(&cap.internal_name), cap.group, cap.minimum_amount, cap.filler_distribution_weight
+    );
+    if let Some(carrying) = cap.carrying.as_ref() {
+        description.push_str(&format!(", carrying {}", display_name(carrying)));
+    }
+    if let Some(spawn_method) = cap.spawn_method.as_ref() {
+        description.push_str(&format!(", spawn method {}", spawn_method));
+    }
+    description
+}
+
+/// What each spawn group is used for. Teki groups and spawn point groups share these
+/// numbers, except that group 5 teki spawn in door seams rather than at spawn points.
+pub(super) fn spawn_group_name(group: u16) -> &'static str {
+    match group {
+        0 => "easy teki",
+        1 => "hard teki",
+        2 => "treasures",
+        4 => "hole/geyser",
+        5 => "seam teki",
+        6 => "plants",
+        7 => "research pod",
+        8 => "special teki",
+        9 => "alcove",
+        _ => "unknown",
+    }
+}
+
+fn spawn_group_color(group: u16) -> Rgba<u8> {
+    match group {
+        0 => Rgba([80, 200, 255, 255]),
+        1 => Rgba([255, 60, 60, 255]),
+        2 => Rgba([255, 215, 0, 255]),
+        4 => Rgba([255, 255, 255, 255]),
+        6 => Rgba([60, 220, 60, 255]),
+        7 => Rgba([255, 140, 200, 255]),
+        8 => Rgba([200, 120, 255, 255]),
+        9 => Rgba([255, 150, 40, 255]),
+        _ => Rgba([128, 128, 128, 255]),
+    }
+}
 
-    let img_x = (((x / 170.0) - min_map_x as f32) * 8.0 * (RENDER_SCALE as f32) - (texture.width() as f32 / 2.0)) as i32;
-    let img_z = (((z / 170.0) - min_map_z as f32) * 8.0 * (RENDER_SCALE as f32) - (texture.height() as f32 / 2.0)) as i32;
+/// Renders every sublevel of a cave stacked top to bottom, each labelled with its name
+/// and seed.
+pub fn render_cave(cave: &CaveLayout, region: Region) -> RenderResult {
+    debug!("Generating cave image for {}.", cave.cave_name);
+    let label_height = CARD_HEADING_SIZE as u32 + CARD_MARGIN;
+    let renders: Vec<RenderResult> = cave.sublevels.iter().map(|layout| render_layout(layout, region)).collect();
+    let width = renders.iter().map(|render| render.image.width()).max().unwrap_or_default();
+    let height = renders.iter().map(|render| render.image.height() + label_height).sum();
+
+    let mut canvas = DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([25, 25, 30, 255])));
+    let mut warnings = Vec::new();
+    let mut y = 0;
+    for (layout, render) in cave.sublevels.iter().zip(renders.into_iter()) {
+        let label = format!("{} {:#010X}", layout.cave_name, layout.starting_seed);
+        draw_text(&mut canvas, &label, CARD_MARGIN as i32, (y + CARD_MARGIN / 2) as i32, CARD_HEADING_SIZE, Rgba([255, 255, 255, 255]));
+        y += label_height;
+        blend(&mut canvas, &render.image, 0, y as i32);
+        y += render.image.height();
+        for warning in render.warnings {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+
+    RenderResult {
+        image: canvas,
+        warnings,
+    }
+}
+
+// x and y are world coordinates, not image or map unit coordinates
+fn draw_object_at<Tex: Textured>(image_buffer: &mut DynamicImage, obj: &Tex, x: f32, z: f32, min_map_x: isize, min_map_z: isize, scale: f32, region: Region, warnings: &mut Vec<RenderWarning>) {
+    let center_x = ((x / 170.0) - min_map_x as f32) * 8.0 * (RENDER_SCALE as f32);
+    let center_z = ((z / 170.0) - min_map_z as f32) * 8.0 * (RENDER_SCALE as f32);
+    draw_icon_at(image_buffer, obj, center_x, center_z, (32.0 * scale) as u32, region, warnings);
+}
+
+// center_x and center_z are image coordinates
+fn draw_icon_at(image_buffer: &mut DynamicImage, obj: &dyn Textured, center_x: f32, center_z: f32, size: u32, region: Region, warnings: &mut Vec<RenderWarning>) {
+    let texture = obj.get_texture(region, Some((size, size)))
+        .unwrap_or_else(|warning| placeholder_for(warning, size, size, warnings));
+
+    let img_x = (center_x - (texture.width() as f32 / 2.0)) as i32;
+    let img_z = (center_z - (texture.height() as f32 / 2.0)) as i32;
 
     blend(image_buffer, &texture, img_x, img_z);
 
     for modifier in obj.get_texture_modifiers().iter() {
         match modifier {
             TextureModifier::Falling => {
-                let falling_icon_texture = image::load_from_memory(get_resource_file_bytes("resources/enemytex_special/falling_icon.png")
-                    .unwrap().as_ref()).unwrap()
-                    .resize(14, 14, FilterType::Lanczos3);
+                let falling_icon_texture = read_named_texture("falling icon", "resources/enemytex_special/falling_icon.png", Some((14, 14)), region)
+                    .unwrap_or_else(|warning| placeholder_for(warning, 14, 14, warnings));
                 blend(image_buffer, &falling_icon_texture, img_x - 5, img_z);
             },
             TextureModifier::Carrying(carrying) => {
-                let carried_treasure_icon = read_image_file(&format!("assets/resulttex/us/arc.d/{}/texture.bti.png", carrying))
-                    .unwrap()
-                    .resize(24, 24, FilterType::Lanczos3);
+                let carried_treasure_icon = treasure_texture(carrying, Some((24, 24)), region)
+                    .unwrap_or_else(|warning| placeholder_for(warning, 24, 24, warnings));
                 blend(image_buffer, &carried_treasure_icon, img_x + 10, img_z + 10);
-            }
+            },
+            TextureModifier::GateHp(health) => {
+                // Centered on the bottom edge of the gate icon
+                let badge = hp_badge(*health);
+                let badge_x = img_x + (texture.width() as i32 - badge.width() as i32) / 2;
+                let badge_z = img_z + texture.height() as i32 - (badge.height() as i32 / 2);
+                blend(image_buffer, &badge, badge_x, badge_z);
+            },
         }
     }
 }
@@ -157,31 +516,248 @@ fn blend(base: &mut DynamicImage, top: &DynamicImage, x: i32, z: i32) {
     }
 }
 
-fn read_image_file(path: &str) -> Result<DynamicImage, Box<dyn Error>> {
-    Ok(
-        image::load_from_memory_with_format(
-            get_file_bytes(path)
-            .ok_or(format!("Couldn't find texture file '{}'", path))?
-            .as_ref(),
-            ImageFormat::Png
-        )?
-    )
+fn fill_rect(canvas: &mut DynamicImage, x: f32, z: f32, width: f32, height: f32, color: Rgba<u8>) {
+    let rect = DynamicImage::ImageRgba8(RgbaImage::from_pixel(width.round() as u32, height.round() as u32, color));
+    blend(canvas, &rect, x.round() as i32, z.round() as i32);
+}
+
+fn draw_circle(canvas: &mut DynamicImage, center_x: f32, center_z: f32, radius: f32, color: Rgba<u8>) {
+    for px in (center_x - radius).floor() as i32 ..= (center_x + radius).ceil() as i32 {
+        for pz in (center_z - radius).floor() as i32 ..= (center_z + radius).ceil() as i32 {
+            let distance = ((px as f32 + 0.5 - center_x).powi(2) + (pz as f32 + 0.5 - center_z).powi(2)).sqrt();
+            if distance <= radius {
+                blend_pixel(canvas, px, pz, color);
+            }
+        }
+    }
+}
+
+/// Draws an arrow starting at (x, z) and pointing in one of the four door directions.
+fn draw_arrow(canvas: &mut DynamicImage, x: f32, z: f32, direction: u16, length: f32, color: Rgba<u8>) {
+    let (dx, dz) = match direction {
+        0 => (0.0, -1.0),
+        1 => (1.0, 0.0),
+        2 => (0.0, 1.0),
+        3 => (-1.0, 0.0),
+        _ => panic!("Invalid door direction"),
+    };
+    let length = length.round() as i32;
+    let head_length = length * 2 / 5;
+    for step in 0..length {
+        // The shaft is 3 pixels wide and the head tapers from its full width down to a point.
+        let half_width = if step < length - head_length { 1 } else { length - step };
+        for offset in -half_width..=half_width {
+            let px = x + dx * step as f32 + dz * offset as f32;
+            let pz = z + dz * step as f32 + dx * offset as f32;
+            blend_pixel(canvas, px.round() as i32, pz.round() as i32, color);
+        }
+    }
+}
+
+fn blend_pixel(canvas: &mut DynamicImage, x: i32, z: i32, color: Rgba<u8>) {
+    if x < 0 || z < 0 || x >= canvas.width() as i32 || z >= canvas.height() as i32 {
+        return;
+    }
+    let mut pixel = canvas.get_pixel(x as u32, z as u32);
+    pixel.blend(&color);
+    canvas.put_pixel(x as u32, z as u32, pixel);
+}
+
+/// Draws a single line of text with its top left corner at (x, y).
+fn draw_text(canvas: &mut DynamicImage, text: &str, x: i32, y: i32, font_size: f32, color: Rgba<u8>) {
+    let ascent = FONT.horizontal_line_metrics(font_size).map(|m| m.ascent).unwrap_or(font_size);
+    let baseline = y + ascent.round() as i32;
+    let mut pen_x = x as f32;
+    for c in text.chars() {
+        let (metrics, coverage) = FONT.rasterize(c, font_size);
+        let glyph_x = pen_x.round() as i32 + metrics.xmin;
+        let glyph_y = baseline - metrics.height as i32 - metrics.ymin;
+        for (i, alpha) in coverage.into_iter().enumerate() {
+            let px = glyph_x + (i % metrics.width) as i32;
+            let py = glyph_y + (i / metrics.width) as i32;
+            if alpha == 0 || px < 0 || py < 0 || px >= canvas.width() as i32 || py >= canvas.height() as i32 {
+                continue;
+            }
+            let mut pixel = canvas.get_pixel(px as u32, py as u32);
+            let mut text_color = color;
+            text_color.0[3] = ((color.0[3] as u16 * alpha as u16) / 255) as u8;
+            pixel.blend(&text_color);
+            canvas.put_pixel(px as u32, py as u32, pixel);
+        }
+        pen_x += metrics.advance_width;
+    }
+}
+
+/// Width in pixels of a single line of text as drawn by `draw_text`.
+fn text_width(text: &str, font_size: f32) -> u32 {
+    text.chars().map(|c| FONT.metrics(c, font_size).advance_width).sum::<f32>().ceil() as u32
+}
+
+/// Small label showing a gate's HP, e.g. "1500".
+fn hp_badge(health: f32) -> DynamicImage {
+    let text = format!("{}", health.round() as u32);
+    let font_size = 12.0;
+    let mut badge = DynamicImage::ImageRgba8(RgbaImage::from_pixel(text_width(&text, font_size) + 4, font_size as u32 + 2, Rgba([20, 20, 20, 210])));
+    draw_text(&mut badge, &text, 2, 0, font_size, Rgba([255, 255, 255, 255]));
+    badge
+}
+
+/// Gates are tinted according to how much HP they have so strong gates stand out at a
+/// glance even without reading the HP badge:
+/// under 1000 HP is left gray, under 2000 is yellow, under 3000 is orange, and anything
+/// stronger is red.
+/// The game uses the same gate model whatever the HP, so these tiers are Caveripper's
+/// own. The thresholds are round numbers picked for readability, not values from the
+/// game; the HP badge is what to go by when the exact number matters.
+fn gate_tint(health: f32) -> Option<Rgba<u8>> {
+    match health {
+        h if h < 1000.0 => None,
+        h if h < 2000.0 => Some(Rgba([255, 230, 90, 255])),
+        h if h < 3000.0 => Some(Rgba([255, 150, 50, 255])),
+        _ => Some(Rgba([240, 60, 60, 255])),
+    }
+}
+
+/// Recolors an image by replacing each pixel's color with the tint color scaled by the
+/// pixel's brightness. Transparency is kept as-is.
+fn tint(image: &DynamicImage, tint: Rgba<u8>) -> DynamicImage {
+    let mut tinted = image.to_rgba8();
+    for pixel in tinted.pixels_mut() {
+        let brightness = pixel.to_luma().0[0] as u16;
+        for channel in 0..3 {
+            pixel.0[channel] = ((tint.0[channel] as u16 * brightness) / 255) as u8;
+        }
+    }
+    DynamicImage::ImageRgba8(tinted)
+}
+
+/// A stand-in for textures that can't be found: a magenta-bordered box with the
+/// object's name written on it, so missing textures are obvious but don't stop the
+/// rest of the image from rendering.
+fn placeholder_texture(name: &str, width: u32, height: u32) -> DynamicImage {
+    let mut placeholder = DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([40, 40, 40, 220])));
+    for x in 0..width {
+        for y in 0..height {
+            if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                placeholder.put_pixel(x, y, Rgba([255, 0, 255, 255]));
+            }
+        }
+    }
+
+    // Shrink the name until it fits inside the box.
+    let mut font_size = (height as f32 / 2.0).max(4.0);
+    while font_size > 4.0 && text_width(name, font_size) + 2 > width {
+        font_size -= 0.5;
+    }
+    let text_x = (width as i32 - text_width(name, font_size) as i32) / 2;
+    let text_y = (height as i32 - font_size as i32) / 2;
+    draw_text(&mut placeholder, name, text_x, text_y, font_size, Rgba([255, 255, 255, 255]));
+    placeholder
+}
+
+/// Records the warning and returns a placeholder for the missing texture.
+fn placeholder_for(warning: RenderWarning, width: u32, height: u32, warnings: &mut Vec<RenderWarning>) -> DynamicImage {
+    let placeholder = match &warning {
+        RenderWarning::MissingTexture { name, .. } => placeholder_texture(&display_name(name), width, height),
+    };
+    if !warnings.contains(&warning) {
+        warnings.push(warning);
+    }
+    placeholder
+}
+
+/// Loads a texture from the extracted game assets, Caveripper's own resources folder,
+/// or (for textures registered at runtime) anywhere on disk, optionally resized to fit
+/// in the given (width, height) box.
+/// Results are cached by path and size, so each texture only has to be decoded and
+/// resized once no matter how many times it gets drawn. This matters a lot when
+/// rendering many layouts in a row. The region is part of the key too, since the same
+/// asset path can point to a different region's file.
+#[cached(
+    result = true,
+    key = "(String, Option<(u32, u32)>, Region)",
+    convert = r#"{ (path.to_owned(), size, region) }"#
+)]
+pub(super) fn read_texture(path: &str, size: Option<(u32, u32)>, region: Region) -> Result<DynamicImage, Box<dyn Error>> {
+    match size {
+        // Resize from the cached full-size texture rather than decoding it again.
+        Some((width, height)) => Ok(read_texture(path, None, region)?.resize(width, height, FilterType::Lanczos3)),
+        None => {
+            let bytes = if path.starts_with("resources/") {
+                get_resource_file_bytes(path)
+            } else if path.starts_with("assets/") {
+                get_file_bytes(path, region)
+            } else {
+                std::fs::read(path).ok().map(Cow::Owned)
+            };
+            Ok(image::load_from_memory(bytes.ok_or(format!("Couldn't find texture file '{}'", path))?.as_ref())?)
+        }
+    }
+}
+
+/// The gate icon with its HP tier's tint applied. Tinting goes over every pixel, so the
+/// tinted textures are cached too.
+#[cached(
+    result = true,
+    key = "(Option<(u32, u32)>, Option<[u8; 4]>, Region)",
+    convert = r#"{ (size, color.map(|color| color.0), region) }"#
+)]
+fn gate_texture(size: Option<(u32, u32)>, color: Option<Rgba<u8>>, region: Region) -> Result<DynamicImage, RenderWarning> {
+    let texture = read_named_texture("gate", "resources/enemytex_special/Gray_bramble_gate_icon.png", size, region)?;
+    match color {
+        Some(color) => Ok(tint(&texture, color)),
+        None => Ok(texture),
+    }
+}
+
+/// Same as `read_texture`, but turns failures into a warning naming the object the
+/// texture was for.
+fn read_named_texture(name: &str, path: &str, size: Option<(u32, u32)>, region: Region) -> Result<DynamicImage, RenderWarning> {
+    read_texture(path, size, region).map_err(|_| RenderWarning::MissingTexture { name: name.to_string(), path: path.to_string() })
+}
+
+fn teki_texture(internal_name: &str, size: Option<(u32, u32)>, region: Region) -> Result<DynamicImage, RenderWarning> {
+    let filename = get_custom_texture_path(internal_name)
+        .or_else(|| get_special_texture_name(internal_name).map(|special_name| format!("resources/enemytex_special/{}", special_name)))
+        .unwrap_or_else(|| format!("assets/enemytex/arc.d/{}/texture.bti.png", internal_name));
+    read_named_texture(internal_name, &filename, size, region)
+}
+
+fn treasure_texture(internal_name: &str, size: Option<(u32, u32)>, region: Region) -> Result<DynamicImage, RenderWarning> {
+    let filename = match get_custom_texture_path(internal_name) {
+        Some(path) => path,
+        None => {
+            let folder = treasure_texture_folder(region).map_err(|e| RenderWarning::MissingTexture {
+                name: internal_name.to_string(),
+                path: match e {
+                    CaveInfoError::MissingFileError(path) | CaveInfoError::FileReadError(path, _) => path,
+                    e => e.to_string(),
+                },
+            })?;
+            format!("assets/resulttex/{}/arc.d/{}/texture.bti.png", folder, internal_name)
+        },
+    };
+    read_named_texture(internal_name, &filename, size, region)
 }
 
 enum TextureModifier {
     Falling,
     Carrying(String),
+    GateHp(f32),
 }
 
 trait Textured {
-    fn get_texture(&self) -> DynamicImage;
+    /// Game textures come from `region`'s assets. `size` is the (width, height) box the
+    /// texture should be resized to fit in. `None` returns the texture at its original
+    /// size.
+    fn get_texture(&self, region: Region, size: Option<(u32, u32)>) -> Result<DynamicImage, RenderWarning>;
     fn get_texture_modifiers(&self) -> Vec<TextureModifier>;
 }
 
 impl Textured for PlacedMapUnit {
-    fn get_texture(&self) -> DynamicImage {
+    fn get_texture(&self, region: Region, size: Option<(u32, u32)>) -> Result<DynamicImage, RenderWarning> {
         let filename = format!("assets/arc/{}/arc.d/texture.bti.png", &self.unit.unit_folder_name);
-        read_image_file(&filename).unwrap()
+        read_named_texture(&self.unit.unit_folder_name, &filename, size, region)
     }
 
     fn get_texture_modifiers(&self) -> Vec<TextureModifier> {
@@ -190,17 +766,8 @@ impl Textured for PlacedMapUnit {
 }
 
 impl Textured for TekiInfo {
-    fn get_texture(&self) -> DynamicImage {
-        match get_special_texture_name(&self.internal_name) {
-            Some(special_name) => {
-                let filename = format!("resources/enemytex_special/{}", special_name);
-                image::load_from_memory(get_resource_file_bytes(&filename).unwrap().as_ref()).unwrap()
-            },
-            None => {
-                let filename = format!("assets/enemytex/arc.d/{}/texture.bti.png", &self.internal_name);
-                read_image_file(&filename).unwrap()
-            }
-        }
+    fn get_texture(&self, region: Region, size: Option<(u32, u32)>) -> Result<DynamicImage, RenderWarning> {
+        teki_texture(&self.internal_name, size, region)
     }
 
     fn get_texture_modifiers(&self) -> Vec<TextureModifier> {
@@ -216,19 +783,10 @@ impl Textured for TekiInfo {
 }
 
 impl Textured for CapInfo {
-    fn get_texture(&self) -> DynamicImage {
+    fn get_texture(&self, region: Region, size: Option<(u32, u32)>) -> Result<DynamicImage, RenderWarning> {
         // We don't consider the possibility of treasures spawning in CapInfo here since that
         // is never done in the vanilla game. May need to fix in the future for romhack support.
-        match get_special_texture_name(&self.internal_name) {
-            Some(special_name) => {
-                let filename = format!("resources/enemytex_special/{}", special_name);
-                image::load_from_memory(get_resource_file_bytes(&filename).unwrap().as_ref()).unwrap()
-            },
-            None => {
-                let filename = format!("assets/enemytex/arc.d/{}/texture.bti.png", self.internal_name);
-                read_image_file(&filename).unwrap()
-            }
-        }
+        teki_texture(&self.internal_name, size, region)
     }
 
     fn get_texture_modifiers(&self) -> Vec<TextureModifier> {
@@ -241,10 +799,8 @@ impl Textured for CapInfo {
 }
 
 impl Textured for ItemInfo {
-    fn get_texture(&self) -> DynamicImage {
-        // TODO: fix US region being hardcoded here.
-        let filename = format!("assets/resulttex/us/arc.d/{}/texture.bti.png", self.internal_name);
-        read_image_file(&filename).unwrap()
+    fn get_texture(&self, region: Region, size: Option<(u32, u32)>) -> Result<DynamicImage, RenderWarning> {
+        treasure_texture(&self.internal_name, size, region)
     }
 
     fn get_texture_modifiers(&self) -> Vec<TextureModifier> {
@@ -253,49 +809,61 @@ impl Textured for ItemInfo {
 }
 
 impl Textured for GateInfo {
-    fn get_texture(&self) -> DynamicImage {
-        let filename = "resources/enemytex_special/Gray_bramble_gate_icon.png";
-        image::load_from_memory(get_resource_file_bytes(filename).unwrap().as_ref()).unwrap()
+    fn get_texture(&self, region: Region, size: Option<(u32, u32)>) -> Result<DynamicImage, RenderWarning> {
+        gate_texture(size, gate_tint(self.health), region)
     }
-    
+
     fn get_texture_modifiers(&self) -> Vec<TextureModifier> {
-        Vec::new()
-        // TODO: gate hp modifier
+        vec![TextureModifier::GateHp(self.health)]
+    }
+}
+
+/// A gate as placed in a layout. Gates in doors facing east or west are drawn rotated
+/// so they line up with the door they're blocking.
+struct PlacedGate<'a> {
+    gate: &'a GateInfo,
+    direction: u16,
+}
+
+impl Textured for PlacedGate<'_> {
+    fn get_texture(&self, region: Region, size: Option<(u32, u32)>) -> Result<DynamicImage, RenderWarning> {
+        let texture = self.gate.get_texture(region, size)?;
+        if self.direction % 2 == 1 {
+            Ok(texture.rotate90())
+        } else {
+            Ok(texture)
+        }
+    }
+
+    fn get_texture_modifiers(&self) -> Vec<TextureModifier> {
+        self.gate.get_texture_modifiers()
     }
 }
 
 impl Textured for SpawnObject {
-    fn get_texture(&self) -> DynamicImage {
+    fn get_texture(&self, region: Region, size: Option<(u32, u32)>) -> Result<DynamicImage, RenderWarning> {
         match self {
-            SpawnObject::Teki(tekiinfo) | SpawnObject::PlantTeki(tekiinfo) => tekiinfo.get_texture(),
+            SpawnObject::Teki(tekiinfo) | SpawnObject::PlantTeki(tekiinfo) => tekiinfo.get_texture(region, size),
             SpawnObject::TekiBunch(tekis) => {
                 // All teki in a bunch will have the same texture
                 let (first_teki, _) = tekis.first().unwrap();
-                first_teki.get_texture()
+                first_teki.get_texture(region, size)
             },
-            SpawnObject::CapTeki(capinfo, _) => capinfo.get_texture(),
-            SpawnObject::Item(iteminfo) => iteminfo.get_texture(),
-            SpawnObject::Gate(gateinfo) => gateinfo.get_texture(),
+            SpawnObject::CapTeki(capinfo, _) => capinfo.get_texture(region, size),
+            SpawnObject::Item(iteminfo) => iteminfo.get_texture(region, size),
+            SpawnObject::Gate(gateinfo) => gateinfo.get_texture(region, size),
             SpawnObject::Hole(plugged) => {
-                let filename = "resources/enemytex_special/Cave_icon.png";
-                let mut base_texture = image::load_from_memory(get_resource_file_bytes(filename).unwrap().as_ref()).unwrap();
+                let mut base_texture = read_named_texture("hole", "resources/enemytex_special/Cave_icon.png", size, region)?;
                 if *plugged {
-                    let plug_filename = "resources/enemytex_special/36px-Clog_icon.png";
-                    let plug_icon = image::load_from_memory(get_resource_file_bytes(plug_filename).unwrap().as_ref()).unwrap()
+                    let plug_icon = read_named_texture("clog", "resources/enemytex_special/36px-Clog_icon.png", None, region)?
                         .resize_exact(base_texture.width(), base_texture.height(), FilterType::Lanczos3);
                     blend(&mut base_texture, &plug_icon, 0, 0);
                 }
-                base_texture
+                Ok(base_texture)
             },
-            SpawnObject::Geyser => {
-                let filename = "resources/enemytex_special/Geyser_icon.png";
-                image::load_from_memory(get_resource_file_bytes(filename).unwrap().as_ref()).unwrap()
-            },
-            SpawnObject::Ship => {
-                let filename = "resources/enemytex_special/pod_icon.png";
-                image::load_from_memory(get_resource_file_bytes(filename).unwrap().as_ref()).unwrap()
-            },
-            _ => DynamicImage::ImageRgb8(RgbImage::new(0, 0)),
+            SpawnObject::Geyser => read_named_texture("geyser", "resources/enemytex_special/Geyser_icon.png", size, region),
+            SpawnObject::Ship => read_named_texture("ship", "resources/enemytex_special/pod_icon.png", size, region),
+            _ => Ok(DynamicImage::ImageRgb8(RgbImage::new(0, 0))),
         }
     }
 
@@ -314,11 +882,15 @@ impl Textured for SpawnObject {
 }
 
 impl Textured for DynamicImage {
-    fn get_texture(&self) -> DynamicImage {
-        self.clone()
+    fn get_texture(&self, _region: Region, size: Option<(u32, u32)>) -> Result<DynamicImage, RenderWarning> {
+        // Images passed in directly aren't cached, so avoid resizing them if we don't have to.
+        match size {
+            Some((width, height)) if (width, height) != self.dimensions() => Ok(self.resize(width, height, FilterType::Lanczos3)),
+            _ => Ok(self.clone()),
+        }
     }
 
     fn get_texture_modifiers(&self) -> Vec<TextureModifier> {
         Vec::new()
     }
-}
\ No newline at end of file
+}
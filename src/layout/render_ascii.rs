@@ -0,0 +1,166 @@
+/// Plain text rendering of layouts, for when viewing images isn't an option (e.g. when
+/// searching over SSH). This uses the same map tile and world coordinate logic as
+/// `render_layout`, just with characters instead of pixels.
+
+use itertools::Itertools;
+
+use crate::caveinfo::{display_name, RoomType};
+use super::{Layout, SpawnObject};
+
+// Each map tile is drawn as a block of this many characters. Terminal characters are
+// about twice as tall as they are wide, so this keeps map tiles looking roughly square.
+const CELL_WIDTH: usize = 4;
+const CELL_HEIGHT: usize = 2;
+
+/// Marker characters for each kind of object along with their descriptions for the
+/// legend. Objects further down the list are drawn on top of earlier ones when they
+/// end up on the same character.
+const LEGEND: [(char, &str); 11] = [
+    ('p', "Plant-group teki"),
+    ('e', "Teki"),
+    ('c', "Cap teki"),
+    ('f', "Falling cap teki"),
+    ('s', "Seam teki (door hazard)"),
+    ('t', "Teki carrying a treasure"),
+    ('T', "Treasure"),
+    ('#', "Gate"),
+    ('G', "Geyser"),
+    ('H', "Exit hole (P if plugged)"),
+    ('S', "Research Pod (start)"),
+];
+
+pub fn render_layout_ascii(layout: &Layout) -> String {
+    let (min_map_x, min_map_z, max_map_x, max_map_z) = layout.map_bounds();
+    let width = (max_map_x - min_map_x) as usize * CELL_WIDTH + 1;
+    let height = (max_map_z - min_map_z) as usize * CELL_HEIGHT + 1;
+    let mut canvas = vec![vec![' '; width]; height];
+
+    // Draw map unit outlines. Rooms are left empty inside while hallways and caps
+    // are filled in so they're easy to tell apart.
+    for map_unit in layout.map_units.iter() {
+        let left = (map_unit.x - min_map_x) as usize * CELL_WIDTH;
+        let top = (map_unit.z - min_map_z) as usize * CELL_HEIGHT;
+        let right = left + map_unit.unit.width as usize * CELL_WIDTH;
+        let bottom = top + map_unit.unit.height as usize * CELL_HEIGHT;
+        let fill = match map_unit.unit.room_type {
+            RoomType::Room => ' ',
+            RoomType::Hallway | RoomType::DeadEnd => '.',
+        };
+
+        for row in top..=bottom {
+            for col in left..=right {
+                let horizontal_edge = row == top || row == bottom;
+                let vertical_edge = col == left || col == right;
+                canvas[row][col] = match (horizontal_edge, vertical_edge) {
+                    (true, true) => '+',
+                    (true, false) => '-',
+                    (false, true) => '|',
+                    (false, false) => fill,
+                };
+            }
+        }
+    }
+
+    // Open up the walls wherever there's a door.
+    for door in layout.map_units.iter().flat_map(|unit| unit.doors.iter()) {
+        let door = door.borrow();
+        let col = (door.x - min_map_x) as usize * CELL_WIDTH;
+        let row = (door.z - min_map_z) as usize * CELL_HEIGHT;
+        match door.door_unit.direction {
+            0 | 2 => (col+1..col+CELL_WIDTH).for_each(|c| canvas[row][c] = ' '),
+            1 | 3 => (row+1..row+CELL_HEIGHT).for_each(|r| canvas[r][col] = ' '),
+            _ => panic!("Invalid door direction in ASCII render"),
+        }
+    }
+
+    // Collect the markers for every spawned object.
+    let mut markers: Vec<(f32, f32, char)> = Vec::new();
+    let mut treasures: Vec<String> = Vec::new();
+    for spawn_point in layout.map_units.iter().flat_map(|unit| unit.spawnpoints.iter()) {
+        match spawn_point.contains.as_ref() {
+            Some(SpawnObject::TekiBunch(teki_list)) => {
+                for (tekiinfo, (dx, _, dz)) in teki_list.iter() {
+                    let marker = if tekiinfo.carrying.is_some() { 't' } else { 'e' };
+                    markers.push((spawn_point.x + dx, spawn_point.z + dz, marker));
+                }
+            },
+            Some(SpawnObject::Teki(tekiinfo)) => markers.push((spawn_point.x, spawn_point.z, if tekiinfo.carrying.is_some() { 't' } else { 'e' })),
+            Some(SpawnObject::PlantTeki(_)) => markers.push((spawn_point.x, spawn_point.z, 'p')),
+            Some(SpawnObject::CapTeki(capinfo, _)) => markers.push((spawn_point.x, spawn_point.z, if capinfo.carrying.is_some() { 't' } else { 'c' })),
+            Some(SpawnObject::Item(_)) => markers.push((spawn_point.x, spawn_point.z, 'T')),
+            Some(SpawnObject::Hole(plugged)) => markers.push((spawn_point.x, spawn_point.z, if *plugged { 'P' } else { 'H' })),
+            Some(SpawnObject::Geyser) => markers.push((spawn_point.x, spawn_point.z, 'G')),
+            Some(SpawnObject::Ship) => markers.push((spawn_point.x, spawn_point.z, 'S')),
+            Some(SpawnObject::Gate(_)) | Some(SpawnObject::TekiDuplicate) | None => {},
+        }
+        if let Some(spawn_object) = spawn_point.contains.as_ref() {
+            treasures.extend(treasure_descriptions(spawn_object));
+        }
+
+        // Falling cap teki are offset the same way as in the image renderer.
+        if let Some(spawn_object) = spawn_point.falling_cap_teki.as_ref() {
+            markers.push((spawn_point.x - 30.0, spawn_point.z - 30.0, 'f'));
+            treasures.extend(treasure_descriptions(spawn_object));
+        }
+    }
+    for door in layout.map_units.iter().flat_map(|unit| unit.doors.iter()) {
+        let (x, z) = door.borrow().world_position();
+        match door.borrow().seam_spawnpoint.as_ref() {
+            Some(SpawnObject::Gate(_)) => markers.push((x, z, '#')),
+            Some(spawn_object @ SpawnObject::Teki(tekiinfo)) => {
+                markers.push((x, z, if tekiinfo.carrying.is_some() { 't' } else { 's' }));
+                treasures.extend(treasure_descriptions(spawn_object));
+            },
+            _ => {},
+        }
+    }
+
+    // Draw markers in legend order so the most important ones end up on top.
+    markers.sort_by_key(|(_, _, marker)| marker_priority(*marker));
+    for (x, z, marker) in markers {
+        let col = (((x / 170.0) - min_map_x as f32) * CELL_WIDTH as f32).round() as usize;
+        let row = (((z / 170.0) - min_map_z as f32) * CELL_HEIGHT as f32).round() as usize;
+        canvas[row.min(height - 1)][col.min(width - 1)] = marker;
+    }
+
+    let mut output = format!("{} {:#010X}\n", layout.cave_name, layout.starting_seed);
+    for row in canvas {
+        output.push_str(row.into_iter().collect::<String>().trim_end());
+        output.push('\n');
+    }
+
+    output.push_str("\nLegend:\n");
+    for (marker, description) in LEGEND.iter().rev() {
+        output.push_str(&format!("  {}  {}\n", marker, description));
+    }
+    output.push_str("  .  Hallway or cap\n");
+    if !treasures.is_empty() {
+        output.push_str(&format!("\nTreasures: {}\n", treasures.into_iter().sorted().join(", ")));
+    }
+
+    output
+}
+
+/// Every treasure in a spawn object, for the list under the map. Treasures carried by
+/// teki say which teki has them, since they're drawn as the teki rather than a 'T'.
+fn treasure_descriptions(spawn_object: &SpawnObject) -> Vec<String> {
+    let carried = |carrying: &Option<String>, teki: &str| {
+        carrying.as_ref().map(|treasure| format!("{} (carried by {})", display_name(treasure), display_name(teki)))
+    };
+    match spawn_object {
+        SpawnObject::Item(iteminfo) => vec![display_name(&iteminfo.internal_name)],
+        SpawnObject::Teki(tekiinfo) => carried(&tekiinfo.carrying, &tekiinfo.internal_name).into_iter().collect(),
+        SpawnObject::TekiBunch(teki_list) => teki_list.iter()
+            .filter_map(|(tekiinfo, _)| carried(&tekiinfo.carrying, &tekiinfo.internal_name))
+            .collect(),
+        SpawnObject::CapTeki(capinfo, _) => carried(&capinfo.carrying, &capinfo.internal_name).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn marker_priority(marker: char) -> usize {
+    match marker {
+        'P' => marker_priority('H'),
+        _ => LEGEND.iter().position(|(m, _)| *m == marker).unwrap_or(0),
+    }
+}
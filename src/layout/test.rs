@@ -1,13 +1,14 @@
 use itertools::Itertools;
 use rand::{Rng, SeedableRng, rngs::SmallRng};
-use regex::Regex;
-use lazy_static::lazy_static;
 use rayon::prelude::*;
 use std::process::Command;
 
 use crate::{caveinfo::force_load_all, layout::boxes_overlap};
-use crate::caveinfo::ALL_SUBLEVELS_MAP;
-use crate::layout::Layout;
+use crate::caveinfo::{display_name, CaveInfoError, FloorInfoBuilder, Region, SublevelId, ALL_SUBLEVELS_MAP, EC1, SH6};
+use crate::layout::{Layout, SpawnObject};
+use crate::layout::cave::{CaveLayout, SeedRule, TimeModel};
+use crate::layout::render::{read_texture, render_cave_unit, render_caveinfo, spawn_group_name};
+use crate::layout::render_ascii::render_layout_ascii;
 
 #[test]
 fn test_collision() {
@@ -18,7 +19,7 @@ fn test_collision() {
 fn test_slugs() {
     let num_layouts = 100;
     let mut rng: SmallRng = SeedableRng::seed_from_u64(0x12345678);
-    force_load_all();
+    force_load_all().unwrap();
 
     let tests: Vec<(u32, String)> = (0..num_layouts).into_iter()
         .map(|_| {
@@ -34,13 +35,13 @@ fn test_slugs() {
 
     let results: Vec<(u32, String, bool, String, String)> = tests.into_par_iter()
         .map(|(seed, sublevel)| {
-            let caveripper_slug: String = Layout::generate(seed, ALL_SUBLEVELS_MAP[&sublevel]).slug();
+            let caveripper_slug: String = Layout::generate(seed, ALL_SUBLEVELS_MAP[&sublevel].as_ref().unwrap()).slug();
 
             let jhawk_cavegen_slug: String = Command::new("java")
                 .arg("-jar")
                 .arg("CaveGen.jar")
                 .arg("cave")
-                .arg(normalize_sublevel(sublevel.as_str()).unwrap_or_else(|| sublevel.to_string()))
+                .arg(sublevel.parse::<SublevelId>().unwrap().cavegen_name())
                 .arg("-seed")
                 .arg(format!("{:#010X}", seed))
                 .arg("-noImages")
@@ -70,31 +71,145 @@ fn test_slugs() {
     assert!(accuracy == 1.0, "Accuracy: {:.03}.", accuracy * 100.0);
 }
 
-lazy_static! {
-    static ref SUBLEVEL_ID_RE: Regex = Regex::new(r"([[:alpha:]]{2,5})[_-]?(\d+)").unwrap();
-    static ref CAVES: [&'static str; 42] = [
-        "EC", "SCx", "FC", "HoB", "WFG", "SH", "BK", "CoS", "GK", "SC", "SmC", "SR", "CoC", "DD", "HoH",
-        "AT", "IM", "AD", "GD", "FT", "WF", "GdD", "AS", "SS", "CK", "PoW", "PoM", "EA", "DD",
-        "PP", "BG", "SK", "CwNN", "SnD", "CH", "RH", "SA", "AA", "TC", "ER", "CG", "SD"
-    ];
+#[test]
+fn test_texture_cache() {
+    let path = std::env::temp_dir().join("caveripper_test_texture_cache.png");
+    image::RgbaImage::new(8, 4).save(&path).unwrap();
+    let path = path.to_str().unwrap();
+
+    let texture = read_texture(path, Some((4, 4)), Region::Us).unwrap();
+    assert_eq!((texture.width(), texture.height()), (4, 2));
+
+    // Both the resized and full size textures are cached now, so neither needs the file.
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(read_texture(path, Some((4, 4)), Region::Us).unwrap().width(), 4);
+    assert_eq!(read_texture(path, None, Region::Us).unwrap().width(), 8);
+    assert!(read_texture(path, Some((2, 2)), Region::Us).is_ok());
+}
+
+#[test]
+fn test_render_cave_unit() {
+    for unit in SH6.as_ref().unwrap().unit_definitions.iter() {
+        // Four square panels side by side, each big enough for the unit in any rotation,
+        // under a title row.
+        let panel_size = unit.width.max(unit.height) as u32 * 48 + 180;
+        let image = render_cave_unit(unit, Region::Us).image;
+        assert_eq!((image.width(), image.height()), (panel_size * 4, panel_size + 70), "{}", unit.unit_folder_name);
+    }
 }
 
-/// Transforms a non-strict sublevel specifier (e.g. scx1) into the format Cavegen
-/// expects (SCx-1).
-fn normalize_sublevel(raw: &str) -> Option<String> {
-    let captures = SUBLEVEL_ID_RE.captures(raw)?;
-    let cave_name = captures.get(1)?.as_str();
-    let sublevel = captures.get(2)?.as_str();
-
-    let cave_name_normalized = *CAVES
-        .iter()
-        .find(|cave| cave_name.eq_ignore_ascii_case(cave))?;
-
-    // Handle alts
-    let cave_name_normalized = match cave_name_normalized {
-        "SmC" => "SC",
-        _ => cave_name_normalized,
-    };
-
-    Some(format!("{}-{}", cave_name_normalized, sublevel))
-}
\ No newline at end of file
+#[test]
+fn test_render_caveinfo() {
+    let sh6 = SH6.as_ref().unwrap();
+    let image = render_caveinfo(sh6, Region::Us).image;
+    assert_eq!(image.width(), 1400);
+
+    // Each extra teki adds one row to the Teki section.
+    let extra_teki = sh6.teki_info[0].clone();
+    let with_extra_teki = FloorInfoBuilder::new(sh6).add_teki(extra_teki).build();
+    let taller = render_caveinfo(&with_extra_teki, Region::Us).image;
+    assert_eq!((taller.width(), taller.height()), (1400, image.height() + 38));
+}
+
+/// Group numbers as used by the teki placement steps in `Layout::generate`.
+#[test]
+fn test_spawn_group_names() {
+    let names = [0, 1, 2, 4, 5, 6, 7, 8, 9].map(spawn_group_name);
+    assert_eq!(names, ["easy teki", "hard teki", "treasures", "hole/geyser", "seam teki", "plants", "research pod", "special teki", "alcove"]);
+    assert_eq!(spawn_group_name(3), "unknown");
+}
+
+#[test]
+fn test_seed_rules() {
+    assert_eq!(SeedRule::Same(5).seed_for(3), 5);
+    assert_eq!(SeedRule::Sequential(0xFFFFFFFF).seed_for(2), 1);
+    let per_sublevel = SeedRule::PerSublevel(vec![10, 20]);
+    assert_eq!((0..4).map(|i| per_sublevel.seed_for(i)).collect_vec(), vec![10, 20, 21, 22]);
+}
+
+#[test]
+fn test_time_model() {
+    let model = TimeModel { walk_speed: 100.0, carry_speed: 50.0, seconds_per_treasure: 5.0, seconds_per_gate: 30.0 };
+    // 300/100 to the exit, 200/100 + 200/50 for treasures, 2 treasures and 1 gate.
+    assert_eq!(model.estimate_seconds(300.0, 200.0, 2, 1), 3.0 + 2.0 + 4.0 + 10.0 + 30.0);
+    assert_eq!(model.estimate_seconds(0.0, 0.0, 0, 0), 0.0);
+}
+
+#[test]
+fn test_cave_summary() {
+    let cave = CaveLayout::generate("SH", &SeedRule::Sequential(0x1234ABCD)).unwrap();
+    assert_eq!(cave.sublevels.len(), 7);
+    assert_eq!(cave.sublevels[1].starting_seed, 0x1234ABCE);
+
+    let summary = cave.summary();
+    assert_eq!(summary.sublevels.len(), 7);
+    assert_eq!(summary.total_treasures, summary.sublevels.iter().map(|sublevel| sublevel.treasures.len()).sum::<usize>());
+    for (sublevel, layout) in summary.sublevels.iter().zip(cave.sublevels.iter()) {
+        assert_eq!(sublevel.treasure_names.len(), sublevel.treasures.len());
+        assert_eq!(sublevel.sublevel, layout.cave_name);
+        assert!(sublevel.exit_distance.is_finite() && sublevel.treasure_distance.is_finite());
+
+        let gates = layout.map_units.iter()
+            .flat_map(|map_unit| map_unit.doors.iter())
+            .filter(|door| matches!(door.borrow().seam_spawnpoint, Some(SpawnObject::Gate(_))))
+            .count();
+        assert_eq!(sublevel.gates, gates);
+        let expected_seconds = sublevel.exit_distance / 150.0
+            + sublevel.treasure_distance / 150.0
+            + sublevel.treasure_distance / 90.0
+            + sublevel.treasures.len() as f32 * 10.0
+            + gates as f32 * 20.0;
+        assert!((sublevel.estimated_seconds - expected_seconds).abs() < 0.01);
+    }
+    let total_seconds: f32 = summary.sublevels.iter().map(|sublevel| sublevel.estimated_seconds).sum();
+    assert!((summary.total_estimated_seconds - total_seconds).abs() < 0.01);
+
+    // A slower model can only make the same cave take longer.
+    let slow = TimeModel { walk_speed: 75.0, ..TimeModel::default() };
+    assert!(cave.summary_with(&slow).total_estimated_seconds >= summary.total_estimated_seconds);
+
+    assert!(matches!(CaveLayout::generate("not a cave", &SeedRule::Same(0)), Err(CaveInfoError::InvalidCave(_))));
+}
+
+#[test]
+fn test_render_ascii() {
+    // Every teki apart from plants carries a treasure so carried treasures show up in
+    // the list.
+    let mut floor = EC1.as_ref().unwrap().clone();
+    for teki in floor.teki_info.iter_mut().filter(|teki| teki.group != 6) {
+        teki.carrying = Some("ahiru".to_string());
+    }
+    let layout = Layout::generate(0x1234ABCD, &floor);
+    let text = render_layout_ascii(&layout);
+    let lines = text.lines().collect_vec();
+    assert_eq!(lines[0], "EC1 0x1234ABCD");
+
+    // Each map tile is 4 characters wide and 2 tall, plus the last wall.
+    let (min_x, min_z, max_x, max_z) = layout.map_bounds();
+    let map = &lines[1..=(max_z - min_z) as usize * 2 + 1];
+    assert_eq!(map.iter().map(|row| row.chars().count()).max(), Some((max_x - min_x) as usize * 4 + 1));
+    assert_eq!(lines[map.len() + 1..map.len() + 3], ["", "Legend:"]);
+
+    // Doors are gaps in the walls.
+    let char_at = |row: usize, col: usize| map[row].chars().nth(col).unwrap_or(' ');
+    for door in layout.map_units.iter().flat_map(|unit| unit.doors.iter()) {
+        let door = door.borrow();
+        let (col, row) = ((door.x - min_x) as usize * 4, (door.z - min_z) as usize * 2);
+        let opening = match door.door_unit.direction {
+            0 | 2 => (col + 1..col + 4).map(|col| char_at(row, col)).collect_vec(),
+            _ => vec![char_at(row + 1, col)],
+        };
+        assert!(opening.iter().all(|c| !matches!(c, '-' | '|' | '+')), "door at ({}, {}): {:?}", door.x, door.z, opening);
+    }
+
+    let mut treasures = Vec::new();
+    for (spawn_object, _, _) in layout.spawn_objects() {
+        match spawn_object {
+            SpawnObject::Item(item) => treasures.push(display_name(&item.internal_name)),
+            SpawnObject::Teki(teki) => treasures.push(format!("{} (carried by {})", display_name("ahiru"), display_name(&teki.internal_name))),
+            _ => {},
+        }
+    }
+    assert!(treasures.iter().any(|treasure| treasure.contains("carried by")));
+    assert_eq!(lines.last().unwrap(), &format!("Treasures: {}", treasures.iter().sorted().join(", ")));
+}
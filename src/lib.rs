@@ -5,4 +5,6 @@
 pub mod caveinfo;
 pub mod layout;
 pub mod pikmin_math;
+pub mod search;
+pub mod stats;
 pub(crate) mod assets;
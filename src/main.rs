@@ -1,40 +1,738 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::num::ParseIntError;
-use cavegen::caveinfo::{FloorInfo, ALL_SUBLEVELS_MAP};
-use cavegen::layout::Layout;
-use cavegen::layout::render::render_layout;
-use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use cavegen::caveinfo::{AssetSource, CaveInfo, CaveInfoError, FloorInfo, Lint, SublevelId, ALL_SUBLEVELS_MAP, Region, display_name, register_display_name, register_texture_mapping, set_display_language};
+use cavegen::layout::{Layout, SpawnObject};
+use cavegen::layout::cave::{CaveLayout, SeedRule};
+use cavegen::layout::render::{render_layout, render_caveinfo, render_cave_unit, render_cave, RenderResult};
+use cavegen::layout::render_ascii::render_layout_ascii;
+use cavegen::search::Query;
+use cavegen::search::sweep::Sweep;
+use cavegen::stats::{SublevelStats, Distribution};
+use itertools::Itertools;
+use rayon::prelude::*;
+use serde_json::json;
 use simple_logger::SimpleLogger;
 use structopt::StructOpt;
 
-fn main() -> Result<(), Box<dyn Error>> {
+const SEARCH_BATCH_SIZE: usize = 1000;
+
+fn main() {
+    if let Err(error) = run(Args::from_args()) {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
     if cfg!(debug_assertions) {
         SimpleLogger::new().with_level(log::LevelFilter::max()).init()?;
     }
 
-    let args = Args::from_args();
-    let caveinfo = caveinfo_from_str(&args.sublevel).unwrap();
-    let seed: u32 = from_hex_str(&args.seed)?;
+    let region = args.region;
+
+    for mapping in args.textures.iter() {
+        let (internal_name, path) = mapping.split_once('=')
+            .ok_or_else(|| format!("Texture mappings should look like 'internal_name=path', got '{}'", mapping))?;
+        register_texture_mapping(internal_name, path);
+    }
+    for mapping in args.names.iter() {
+        let (internal_name, name) = mapping.split_once('=')
+            .ok_or_else(|| format!("Name mappings should look like 'internal_name=Display Name', got '{}'", mapping))?;
+        register_display_name(internal_name, name);
+    }
+    match args.language.as_str() {
+        "internal" => set_display_language(None)?,
+        language => set_display_language(Some(language))?,
+    }
+
+    match args.command {
+        Command::Generate { sublevel, seed, format, output } => {
+            let caveinfo = caveinfo_from_str(&sublevel, region)?;
+            let layout = Layout::generate(seed, &caveinfo);
+            match format {
+                OutputFormat::Png => save_render(render_layout(&layout, region), &output)?,
+                OutputFormat::Text => print!("{}", render_layout_ascii(&layout)),
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&layout)?),
+            }
+        },
+        Command::Cave { cave, seeds, sequential, format, output } => {
+            let seed_rule = match seeds.as_slice() {
+                [seed] if sequential => SeedRule::Sequential(*seed),
+                [seed] => SeedRule::Same(*seed),
+                _ => SeedRule::PerSublevel(seeds),
+            };
+            let cave_layout = CaveInfo::load(&AssetSource::Embedded(region), &cave)
+                .map(|caveinfo| CaveLayout::from_caveinfo(&caveinfo, &seed_rule))
+                .map_err(|e| match e {
+                    CaveInfoError::InvalidCave(_) => format!("Unknown cave '{}'. Caves look like 'SH' or 'SCx'", cave),
+                    e => e.to_string(),
+                })?;
+            match format {
+                OutputFormat::Png => save_render(render_cave(&cave_layout, region), &output)?,
+                OutputFormat::Text => {
+                    let summary = cave_layout.summary();
+                    for sublevel in summary.sublevels.iter() {
+                        println!("{} {}: ~{}, exit {:.0} units away, {} gates, {} treasures {:.0} units away in total: {}",
+                            sublevel.sublevel, sublevel.seed, format_seconds(sublevel.estimated_seconds), sublevel.exit_distance, sublevel.gates,
+                            sublevel.treasures.len(), sublevel.treasure_distance, sublevel.treasure_names.join(", "));
+                    }
+                    println!("Total: ~{}, {} treasures, {:.0} units to exits, {:.0} units to treasures",
+                        format_seconds(summary.total_estimated_seconds), summary.total_treasures, summary.total_exit_distance, summary.total_treasure_distance);
+                },
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&cave_layout.summary())?),
+            }
+        },
+        Command::Search { sublevel, query, num, timeout, format, output } => {
+            let caveinfo = caveinfo_from_str(&sublevel, region)?;
+            let seeds = search(&caveinfo, &query, num, Duration::from_secs(timeout));
+            if seeds.is_empty() {
+                return Err(format!("No matching seeds found for '{}' within {} seconds", sublevel, timeout).into());
+            }
+            match format {
+                OutputFormat::Png => {
+                    for seed in seeds {
+                        let layout = Layout::generate(seed, &caveinfo);
+                        save_render(render_layout(&layout, region), &output.join(format!("{}_{:#010X}.png", caveinfo.name(), seed)))?;
+                    }
+                },
+                OutputFormat::Text => seeds.iter().for_each(|seed| println!("{:#010X}", seed)),
+                OutputFormat::Json => {
+                    let seeds: Vec<String> = seeds.iter().map(|seed| format!("{:#010X}", seed)).collect();
+                    println!("{}", serde_json::to_string_pretty(&json!({ "sublevel": caveinfo.name(), "seeds": seeds }))?);
+                },
+            }
+        },
+        Command::Sweep { sublevel, query, checkpoint, chunks, format } => {
+            let caveinfo = caveinfo_from_str(&sublevel, region)?;
+            let checkpoint = checkpoint.unwrap_or_else(|| PathBuf::from(format!("./caveripper_output/sweep_{}.json", caveinfo.name())));
+            let mut sweep = Sweep::new_or_resume(&caveinfo, &query, &checkpoint)?;
+            if sweep.checkpoint.next_chunk > 0 {
+                eprintln!("Resuming from chunk {}/{} with {} matches so far", sweep.checkpoint.next_chunk, sweep.checkpoint.num_chunks(), sweep.checkpoint.num_matches);
+            }
+            sweep.run(chunks, |progress| {
+                eprintln!("Chunk {}/{} ({:.2}%), {} matches", progress.next_chunk, progress.num_chunks(), progress.progress() * 100.0, progress.num_matches);
+            })?;
+
+            match format {
+                OutputFormat::Text => sweep.for_each_match(|seed| println!("{:#010X}", seed))?,
+                OutputFormat::Json => {
+                    // Matches aren't listed since there can be billions of them.
+                    let mut json = serde_json::to_value(&sweep.checkpoint)?;
+                    json["matches_file"] = json!(sweep.matches_path());
+                    println!("{}", serde_json::to_string_pretty(&json)?);
+                },
+                OutputFormat::Png => return Err(format.unsupported("sweep")),
+            }
+            if !sweep.checkpoint.is_finished() {
+                eprintln!("Sweep paused at {:.2}%. Run the same command again to continue.", sweep.checkpoint.progress() * 100.0);
+            }
+        },
+        Command::Stats { sublevel, num, format } => {
+            let caveinfo = caveinfo_from_str(&sublevel, region)?;
+            let stats = SublevelStats::sample(&caveinfo, num);
+            match format {
+                OutputFormat::Text => print_stats(&caveinfo.name(), &stats),
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+                OutputFormat::Png => return Err(format.unsupported("stats")),
+            }
+        },
+        Command::Caveinfo { sublevel, format, output } => {
+            let caveinfo = caveinfo_from_str(&sublevel, region)?;
+            match format {
+                OutputFormat::Png => save_render(render_caveinfo(&caveinfo, region), &output)?,
+                OutputFormat::Text => print_caveinfo(&caveinfo),
+                OutputFormat::Json => println!("{}", caveinfo.to_json()),
+            }
+        },
+        Command::Units { sublevel, format, output } => {
+            let caveinfo = caveinfo_from_str(&sublevel, region)?;
+            // Units in caveinfo are stored in every rotation; only the unrotated ones are needed here.
+            let units = caveinfo.cave_units.iter().filter(|unit| unit.rotation == 0);
+            match format {
+                OutputFormat::Png => {
+                    for unit in units {
+                        save_render(render_cave_unit(unit, region), &output.join(format!("{}.png", unit.unit_folder_name)))?;
+                    }
+                },
+                OutputFormat::Text => {
+                    for unit in units {
+                        println!("{:<28} {}x{}  {:<8} {} doors, {} spawn points",
+                            unit.unit_folder_name, unit.width, unit.height, format!("{:?}", unit.room_type), unit.num_doors, unit.spawn_points.len());
+                    }
+                },
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&units.collect::<Vec<_>>())?),
+            }
+        },
+        Command::Batch { input, format, output } => {
+            let text = match input.as_ref() {
+                Some(path) if path != Path::new("-") => std::fs::read_to_string(path)
+                    .map_err(|e| format!("Couldn't read seed list '{}': {}", path.display(), e))?,
+                _ => std::io::read_to_string(std::io::stdin())?,
+            };
+            let jobs = parse_seed_list(&text, region)?;
+            std::fs::create_dir_all(&output)?;
+
+            let results: Vec<Result<String, String>> = jobs.par_iter()
+                .map(|(caveinfo, seed)| {
+                    let layout = Layout::generate(*seed, caveinfo);
+                    let path = output.join(format!("{}_{:#010X}.{}", caveinfo.name(), seed, format.extension()));
+                    let written = match format {
+                        OutputFormat::Png => save_render(render_layout(&layout, region), &path),
+                        OutputFormat::Text => std::fs::write(&path, render_layout_ascii(&layout)).map_err(Into::into),
+                        OutputFormat::Json => serde_json::to_string_pretty(&layout).map_err(Into::into)
+                            .and_then(|json| std::fs::write(&path, json).map_err(Into::into)),
+                    };
+                    written
+                        .map(|_| summarize(&layout))
+                        .map_err(|e| format!("{} {:#010X}: {}", caveinfo.name(), seed, e))
+                })
+                .collect();
 
-    let layout = Layout::generate(seed, caveinfo);
-    render_layout(&layout);
+            for result in results.iter() {
+                match result {
+                    Ok(summary) => println!("{}", summary),
+                    Err(message) => eprintln!("Error: {}", message),
+                }
+            }
+            let num_failed = results.iter().filter(|result| result.is_err()).count();
+            if num_failed > 0 {
+                return Err(format!("{} of {} layouts failed", num_failed, results.len()).into());
+            }
+        },
+        Command::Diff { cave, old, new, format } => {
+            let old = CaveInfo::load(&old.map_or(AssetSource::Embedded(region), AssetSource::Directory), &cave)?;
+            let new = CaveInfo::load(&new.map_or(AssetSource::Embedded(region), AssetSource::Directory), &cave)?;
+            let diff = old.diff(&new);
+            match format {
+                OutputFormat::Text => print!("{}", diff),
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+                OutputFormat::Png => return Err(format.unsupported("diff")),
+            }
+        },
+        Command::Check { sublevels, assets, format } => {
+            let sublevels = if sublevels.is_empty() {
+                ALL_SUBLEVELS_MAP.keys().cloned().sorted().collect()
+            } else {
+                sublevels
+            };
+            let source = assets.map_or(AssetSource::Embedded(region), AssetSource::Directory);
+            let results = check(&sublevels, &source);
+            match format {
+                OutputFormat::Text => {
+                    for (sublevel, result) in results.iter() {
+                        match result {
+                            Ok(lints) if lints.is_empty() => println!("{}: ok", sublevel),
+                            Ok(lints) => {
+                                println!("{}: {} warning(s)", sublevel, lints.len());
+                                lints.iter().for_each(|lint| println!("  warning: {}", lint));
+                            },
+                            Err(message) => println!("{}: FAILED: {}", sublevel, message),
+                        }
+                    }
+                },
+                OutputFormat::Json => {
+                    let results: Vec<_> = results.iter()
+                        .map(|(sublevel, result)| json!({
+                            "sublevel": sublevel,
+                            "ok": result.is_ok(),
+                            "error": result.as_ref().err(),
+                            "warnings": result.as_ref().ok(),
+                        }))
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&results)?);
+                },
+                OutputFormat::Png => return Err(format.unsupported("check")),
+            }
+            let num_failed = results.iter().filter(|(_, result)| result.is_err()).count();
+            if num_failed > 0 {
+                return Err(format!("{} of {} sublevels failed", num_failed, results.len()).into());
+            }
+        },
+    }
     Ok(())
 }
 
 
 #[derive(StructOpt)]
+#[structopt(about = "Pikmin 2 cave generation and layout analysis tools")]
 struct Args {
-    #[structopt()]
-    sublevel: String,
+    /// Extra textures to use for objects Caveripper doesn't know about, e.g. romhack teki.
+    /// Format: `internal_name=path/to/image.png`. Can be given multiple times.
+    #[structopt(long = "texture", global = true, number_of_values = 1)]
+    textures: Vec<String>,
+
+    /// Language for teki and treasure names, e.g. "en", or "internal" to show the game's
+    /// internal names instead.
+    #[structopt(long, global = true, default_value = "en")]
+    language: String,
+
+    /// Which release of the game to use caveinfo and textures from: us, pal, or jp.
+    /// Assets for regions other than us are extracted into `assets/<region>`.
+    #[structopt(long, global = true, default_value = "us")]
+    region: Region,
 
-    #[structopt()]
-    seed: String,
+    /// Extra display names for objects Caveripper doesn't know about.
+    /// Format: `internal_name=Display Name`. Can be given multiple times.
+    #[structopt(long = "name", global = true, number_of_values = 1)]
+    names: Vec<String>,
+
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(StructOpt)]
+enum Command {
+    /// Generate a single layout.
+    Generate {
+        /// Sublevel to generate, e.g. "SCx6".
+        sublevel: String,
+
+        /// Seed in hex, e.g. "0x1234ABCD".
+        #[structopt(parse(try_from_str = from_hex_str))]
+        seed: u32,
+
+        /// png, text, or json.
+        #[structopt(long, default_value = "png")]
+        format: OutputFormat,
+
+        /// Where to save the image for png output.
+        #[structopt(long, short, default_value = "./caveripper_output/layout.png")]
+        output: PathBuf,
+    },
+
+    /// Generate every sublevel of a cave, e.g. "SH", and list its treasures, how far
+    /// they are from the ship, and a rough time estimate. The estimate assumes a walk
+    /// speed of 150 units/s, a carry speed of 90 units/s, 10s per treasure and 20s per
+    /// gate, so it's only good for comparing layouts.
+    Cave {
+        cave: String,
+
+        /// Seeds in hex. Give one per sublevel, or a single seed to use for every
+        /// sublevel (see --sequential).
+        #[structopt(required = true, parse(try_from_str = from_hex_str))]
+        seeds: Vec<u32>,
+
+        /// When only one seed is given, add one to it for each following sublevel instead
+        /// of reusing it as-is.
+        #[structopt(long)]
+        sequential: bool,
+
+        /// text, json, or png. png renders every sublevel in one image.
+        #[structopt(long, default_value = "text")]
+        format: OutputFormat,
+
+        /// Where to save the image for png output.
+        #[structopt(long, short, default_value = "./caveripper_output/cave.png")]
+        output: PathBuf,
+    },
+
+    /// Search random seeds for layouts matching a query, e.g. "bey_goma >= 1 & gate = 0".
+    Search {
+        sublevel: String,
+
+        query: Query,
+
+        /// How many matching seeds to find.
+        #[structopt(long, short, default_value = "1")]
+        num: usize,
+
+        /// Give up after this many seconds.
+        #[structopt(long, default_value = "60")]
+        timeout: u64,
+
+        /// text, json, or png. png renders every matching layout.
+        #[structopt(long, default_value = "text")]
+        format: OutputFormat,
+
+        /// Folder to save images to for png output.
+        #[structopt(long, short, default_value = "./caveripper_output")]
+        output: PathBuf,
+    },
+
+    /// Search every possible seed for layouts matching a query. Progress is saved to a
+    /// checkpoint file as it goes, and running the same command again resumes from it.
+    Sweep {
+        sublevel: String,
+
+        query: Query,
+
+        /// Checkpoint file to save progress to. Defaults to
+        /// "./caveripper_output/sweep_<sublevel>.json". Matches are saved next to it, e.g. in
+        /// "./caveripper_output/sweep_<sublevel>.matches.txt".
+        #[structopt(long, parse(from_os_str))]
+        checkpoint: Option<PathBuf>,
+
+        /// Stop after searching this many chunks (of 2^20 seeds each) instead of running
+        /// until the whole seed space is covered.
+        #[structopt(long)]
+        chunks: Option<u64>,
+
+        /// text or json. Only matches found so far are printed if the sweep isn't done. JSON
+        /// output is the checkpoint, which points to the matches file instead of listing them.
+        #[structopt(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Show how often each teki, treasure and gate appears across many random seeds.
+    Stats {
+        sublevel: String,
+
+        /// Number of layouts to generate.
+        #[structopt(long, short, default_value = "1000")]
+        num: usize,
+
+        /// text or json.
+        #[structopt(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Show a sublevel's caveinfo parameters.
+    Caveinfo {
+        sublevel: String,
+
+        /// text, json, or png.
+        #[structopt(long, default_value = "text")]
+        format: OutputFormat,
+
+        /// Where to save the image for png output.
+        #[structopt(long, short, default_value = "./caveripper_output/caveinfo.png")]
+        output: PathBuf,
+    },
+
+    /// Show the map units used on a sublevel.
+    Units {
+        sublevel: String,
+
+        /// text, json, or png. png renders each unit in all four rotations.
+        #[structopt(long, default_value = "text")]
+        format: OutputFormat,
+
+        /// Folder to save images to for png output.
+        #[structopt(long, short, default_value = "./caveripper_output/units")]
+        output: PathBuf,
+    },
+
+    /// Generate every layout in a list of seeds. Each line of the list should contain a
+    /// sublevel and a seed, e.g. "SCx6 0x1234ABCD". Blank lines and lines starting with
+    /// '#' are ignored.
+    Batch {
+        /// File to read the seed list from. Reads from stdin if not given or "-".
+        #[structopt(parse(from_os_str))]
+        input: Option<PathBuf>,
+
+        /// png, text, or json. Each layout is written to its own file in the output folder.
+        #[structopt(long, default_value = "png")]
+        format: OutputFormat,
+
+        /// Folder to write each layout to.
+        #[structopt(long, short, default_value = "./caveripper_output/batch")]
+        output: PathBuf,
+    },
+
+    /// Compare a cave's caveinfo between two sets of game files, e.g. vanilla and a
+    /// romhack. Leave out --old or --new to use the built-in files for that side.
+    Diff {
+        cave: String,
+
+        /// Folder with the old game files, laid out like the assets folder.
+        #[structopt(long)]
+        old: Option<PathBuf>,
+
+        /// Folder with the new game files, laid out like the assets folder.
+        #[structopt(long)]
+        new: Option<PathBuf>,
+
+        /// text or json.
+        #[structopt(long, default_value = "text")]
+        format: OutputFormat,
+    },
+
+    /// Check that caveinfo loads and a layout can be generated for the given sublevels,
+    /// or for every sublevel if none are given. Also warns about things like unknown
+    /// teki or missing map units.
+    Check {
+        sublevels: Vec<String>,
+
+        /// Check game files in this folder instead of the built-in ones. It should be laid
+        /// out like the assets folder, e.g. with caveinfo files in `<folder>/caveinfo`.
+        #[structopt(long)]
+        assets: Option<PathBuf>,
+
+        /// text or json.
+        #[structopt(long, default_value = "text")]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Json,
+    Text,
+    Png,
+}
+
+impl OutputFormat {
+    fn unsupported(&self, command: &str) -> Box<dyn Error> {
+        format!("'{}' doesn't support {} output", command, self).into()
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Text => "txt",
+            OutputFormat::Png => "png",
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+    fn from_str(format: &str) -> Result<OutputFormat, String> {
+        match format.to_ascii_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "text" => Ok(OutputFormat::Text),
+            "png" => Ok(OutputFormat::Png),
+            _ => Err(format!("Unknown output format '{}'. Expected json, text, or png", format)),
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Png => write!(f, "png"),
+        }
+    }
 }
 
 fn from_hex_str(src: &str) -> Result<u32, ParseIntError> {
     u32::from_str_radix(src.strip_prefix("0x").unwrap_or(src), 16)
 }
 
-fn caveinfo_from_str(cave: &str) -> Option<&'static Lazy<FloorInfo>> {
-    ALL_SUBLEVELS_MAP.get(&cave.to_ascii_lowercase()).cloned()
+/// Looks up a sublevel by name, or loads a floor from a `.json` or `.toml` file such as
+/// one saved with `caveinfo --format json`.
+fn caveinfo_from_str(cave: &str, region: Region) -> Result<Cow<'static, FloorInfo>, String> {
+    load_sublevel(cave, region, &mut HashMap::new())
+}
+
+/// Loads a sublevel by name, or from a JSON or TOML file. US sublevels come from the
+/// preloaded vanilla caveinfo; other regions' caves are loaded from their own assets
+/// and kept in `caves` so each one is only parsed once.
+fn load_sublevel(cave: &str, region: Region, caves: &mut HashMap<String, Result<CaveInfo, String>>) -> Result<Cow<'static, FloorInfo>, String> {
+    let lowercase = cave.to_ascii_lowercase();
+    if lowercase.ends_with(".json") || lowercase.ends_with(".toml") {
+        let text = std::fs::read_to_string(cave).map_err(|e| format!("Couldn't read '{}': {}", cave, e))?;
+        let floor = if lowercase.ends_with(".json") { FloorInfo::from_json(&text) } else { FloorInfo::from_toml(&text) };
+        return floor.map(Cow::Owned).map_err(|e| e.to_string());
+    }
+    if region != Region::Us {
+        return load_floor(cave, &AssetSource::Embedded(region), caves).map(Cow::Owned);
+    }
+    cave.parse::<SublevelId>()
+        .and_then(|sublevel_id| sublevel_id.caveinfo())
+        .map(Cow::Borrowed)
+        .map_err(|e| match e {
+            CaveInfoError::InvalidSublevel(_) => format!("Unknown sublevel '{}'. Sublevels look like 'SCx6', 'BK-4' or 'Submerged Castle 2'", cave),
+            e => e.to_string(),
+        })
+}
+
+/// Parses a seed list with one "sublevel seed" pair per line. Sublevel and seed can be
+/// separated by whitespace or a comma.
+fn parse_seed_list(text: &str, region: Region) -> Result<Vec<(Cow<'static, FloorInfo>, u32)>, String> {
+    let mut caves = HashMap::new();
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_num, line)| {
+            let (sublevel, seed) = line.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|part| !part.is_empty())
+                .collect_tuple()
+                .ok_or_else(|| format!("Line {}: expected 'sublevel seed', got '{}'", line_num, line))?;
+            let caveinfo = load_sublevel(sublevel, region, &mut caves).map_err(|e| format!("Line {}: {}", line_num, e))?;
+            let seed = from_hex_str(seed).map_err(|_| format!("Line {}: invalid seed '{}'", line_num, seed))?;
+            Ok((caveinfo, seed))
+        })
+        .collect()
+}
+
+/// One-line description of a layout's contents.
+fn format_seconds(seconds: f32) -> String {
+    format!("{}:{:02}", seconds as u32 / 60, seconds as u32 % 60)
+}
+
+fn summarize(layout: &Layout) -> String {
+    let spawn_objects = layout.spawn_objects();
+    let count = |predicate: fn(&SpawnObject) -> bool| spawn_objects.iter().filter(|(spawn_object, _, _)| predicate(spawn_object)).count();
+    format!(
+        "{} {:#010X}: {} map units, {} teki, {} treasures, {} gates",
+        layout.cave_name,
+        layout.starting_seed,
+        layout.map_units.len(),
+        count(|o| matches!(o, SpawnObject::Teki(_) | SpawnObject::PlantTeki(_) | SpawnObject::CapTeki(..))),
+        count(|o| match o {
+            SpawnObject::Item(_) => true,
+            SpawnObject::Teki(tekiinfo) => tekiinfo.carrying.is_some(),
+            SpawnObject::CapTeki(capinfo, _) => capinfo.carrying.is_some(),
+            _ => false,
+        }),
+        count(|o| matches!(o, SpawnObject::Gate(_))),
+    )
+}
+
+fn save_render(render: RenderResult, path: &Path) -> Result<(), Box<dyn Error>> {
+    for warning in render.warnings.iter() {
+        eprintln!("Warning: {}", warning);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    render.save(path)?;
+    Ok(())
+}
+
+/// Generates layouts from random seeds in parallel until enough matching ones are
+/// found or the timeout runs out.
+fn search(caveinfo: &FloorInfo, query: &Query, num: usize, timeout: Duration) -> Vec<u32> {
+    let start = Instant::now();
+    let mut found = Vec::new();
+    while found.len() < num && start.elapsed() < timeout {
+        let seeds: Vec<u32> = (0..SEARCH_BATCH_SIZE).map(|_| rand::random()).collect();
+        found.extend(seeds.into_par_iter().filter(|seed| query.matches(&Layout::generate(*seed, caveinfo))).collect::<Vec<_>>());
+    }
+    found.truncate(num);
+    found
+}
+
+/// Loads each sublevel, lints it, and generates one layout from it. Sublevels that fail
+/// to load, or that have no start spawn point and so can't generate at all, are
+/// reported as errors so the rest can still be checked.
+fn check(sublevels: &[String], source: &AssetSource) -> Vec<(String, Result<Vec<Lint>, String>)> {
+    let mut caves: HashMap<String, Result<CaveInfo, String>> = HashMap::new();
+    sublevels.iter()
+        .map(|sublevel| {
+            let result = load_floor(sublevel, source, &mut caves).and_then(|caveinfo| {
+                let lints = caveinfo.lint(source);
+                if lints.contains(&Lint::NoStartSpawnPoint) {
+                    return Err(format!("{}, so no layout can be generated", Lint::NoStartSpawnPoint));
+                }
+                Layout::generate(0x12345678, &caveinfo);
+                Ok(lints)
+            });
+            (sublevel.clone(), result)
+        })
+        .collect()
+}
+
+/// Loads one sublevel from `source`. Caves loaded from a folder are kept in `caves` so
+/// each one is only parsed once.
+fn load_floor(sublevel: &str, source: &AssetSource, caves: &mut HashMap<String, Result<CaveInfo, String>>) -> Result<FloorInfo, String> {
+    if *source == AssetSource::Embedded(Region::Us) {
+        return caveinfo_from_str(sublevel, Region::Us).map(Cow::into_owned);
+    }
+    let sublevel_id: SublevelId = sublevel.parse()
+        .map_err(|_| format!("Unknown sublevel '{}'. Sublevels look like 'SCx6', 'BK-4' or 'Submerged Castle 2'", sublevel))?;
+    let cave = caves.entry(sublevel_id.cave.clone())
+        .or_insert_with(|| CaveInfo::load(source, &sublevel_id.cave).map_err(|e| e.to_string()));
+    let cave = cave.as_ref().map_err(Clone::clone)?;
+    cave.floors.get(sublevel_id.floor as usize - 1).cloned()
+        .ok_or_else(|| format!("{} only has {} floors", sublevel_id.cave, cave.floors.len()))
+}
+
+fn print_stats(sublevel: &str, stats: &SublevelStats) {
+    let percent = |count: usize| count as f32 * 100.0 / stats.num_layouts.max(1) as f32;
+    let histogram = |distribution: &Distribution| distribution.0.iter()
+        .map(|(count, occurrences)| format!("{}: {:.2}%", count, percent(*occurrences)))
+        .join(", ");
+
+    println!("{} ({} layouts)", sublevel, stats.num_layouts);
+    println!("Teki:");
+    println!("  {:<24} {:>8} {:>5} {:>5} {:>8}", "", "mean", "min", "max", "none");
+    for (name, distribution) in stats.teki.iter() {
+        println!("  {:<24} {:>8.3} {:>5} {:>5} {:>7.2}%",
+            display_name(name), distribution.mean(), distribution.min(), distribution.max(), distribution.probability(0) * 100.0);
+    }
+
+    println!("Treasures by map unit:");
+    for (treasure, units) in stats.treasure_units.iter() {
+        let units = units.iter()
+            .sorted_by_key(|(_, count)| std::cmp::Reverse(**count))
+            .map(|(unit, count)| format!("{} {:.2}%", unit, percent(*count)))
+            .join(", ");
+        println!("  {}: {}", display_name(treasure), units);
+    }
+
+    println!("Exit by map unit:");
+    for (unit, count) in stats.exit_units.iter().sorted_by_key(|(_, count)| std::cmp::Reverse(**count)) {
+        println!("  {:<24} {:>7.2}%", unit, percent(*count));
+    }
+
+    println!("Gates: mean {:.3} ({})", stats.gates.mean(), histogram(&stats.gates));
+    println!("Map units: mean {:.3} ({})", stats.map_units.mean(), histogram(&stats.map_units));
+}
+
+fn print_caveinfo(caveinfo: &FloorInfo) {
+    println!("{}", caveinfo.name());
+    println!("  Max main objects: {}", caveinfo.max_main_objects);
+    println!("  Max treasures: {}", caveinfo.max_treasures);
+    println!("  Max gates: {}", caveinfo.max_gates);
+    println!("  Rooms: {}", caveinfo.num_rooms);
+    println!("  Corridor probability: {}", caveinfo.corridor_probability);
+    println!("  Cap probability: {}", caveinfo.cap_probability);
+    println!("  Geyser: {}", caveinfo.has_geyser);
+    println!("  Exit plugged: {}", caveinfo.exit_plugged);
+    println!("  Final floor: {}", caveinfo.is_final_floor);
+    println!("  Unit file: {}", caveinfo.unit_definition_file);
+    println!("  Lighting file: {}", caveinfo.lighting_file);
+    println!("  Skybox: {}", caveinfo.skybox.as_deref().unwrap_or("none"));
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    println!("  Echo: {}", optional(caveinfo.echo.map(|echo| echo.to_string())));
+    println!("  Music type: {}", optional(caveinfo.music_type.map(|music| music.to_string())));
+    println!("  Hidden floor: {}", optional(caveinfo.hidden_floor.map(|hidden| hidden.to_string())));
+    println!("  Waterwraith: {}", caveinfo.has_waterwraith());
+    println!("  Waterwraith timer: {}", optional(caveinfo.waterwraith_timer.map(|timer| timer.to_string())));
+    println!("  Version: {}", optional(caveinfo.version.map(|version| version.to_string())));
+    for (tag, value) in caveinfo.other_tags.iter() {
+        println!("  {{f{}}}: {}", tag, value);
+    }
+
+    println!("Teki:");
+    for teki in caveinfo.teki_info.iter() {
+        print!("  {} (group {}): min {}, weight {}", display_name(&teki.internal_name), teki.group, teki.minimum_amount, teki.filler_distribution_weight);
+        if let Some(carrying) = teki.carrying.as_ref() {
+            print!(", carrying {}", display_name(carrying));
+        }
+        if let Some(spawn_method) = teki.spawn_method.as_ref() {
+            print!(", spawn method {}", spawn_method);
+        }
+        println!();
+    }
+    println!("Treasures:");
+    for item in caveinfo.item_info.iter() {
+        println!("  {}: min {}, weight {}", display_name(&item.internal_name), item.min_amount, item.filler_distribution_weight);
+    }
+    println!("Cap teki:");
+    for cap in caveinfo.cap_info.iter() {
+        print!("  {} (group {}): min {}, weight {}", display_name(&cap.internal_name), cap.group, cap.minimum_amount, cap.filler_distribution_weight);
+        if let Some(carrying) = cap.carrying.as_ref() {
+            print!(", carrying {}", display_name(carrying));
+        }
+        if let Some(spawn_method) = cap.spawn_method.as_ref() {
+            print!(", spawn method {}", spawn_method);
+        }
+        println!();
+    }
+    println!("Gates:");
+    for gate in caveinfo.gate_info.iter() {
+        println!("  {} HP: weight {}", gate.health, gate.spawn_distribution_weight);
+    }
 }
@@ -0,0 +1,194 @@
+/// Simple queries for finding layouts with particular properties, e.g. seeds with a
+/// certain treasure and no gates: `bey_goma >= 1 & gate = 0`.
+///
+/// Each condition compares the number of matching things in a layout against a fixed
+/// count. Names are matched case-insensitively against teki and treasure internal
+/// names (treasures carried by teki count too), map unit names, teki categories
+/// (`enemy`, `hazard`, `plant`, `egg`, and `candypop`), and the special names `gate`,
+/// `hole`, `geyser`, and `ship`.
+
+pub mod sweep;
+#[cfg(test)]
+mod test;
+
+use std::{error::Error, fmt::{Display, Formatter}, str::FromStr};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::caveinfo::teki_metadata;
+use crate::layout::{Layout, SpawnObject};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub conditions: Vec<Condition>,
+}
+
+impl Query {
+    /// Whether the layout satisfies every condition in this query.
+    pub fn matches(&self, layout: &Layout) -> bool {
+        let spawn_objects = layout.spawn_objects();
+        self.conditions.iter()
+            .all(|condition| condition.comparison.compare(count_matching(layout, &spawn_objects, &condition.name), condition.count))
+    }
+}
+
+impl Display for Query {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.conditions.iter().map(ToString::to_string).collect::<Vec<_>>().join(" & "))
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+    fn from_str(query: &str) -> Result<Query, QueryError> {
+        if query.trim().is_empty() {
+            return Err(QueryError(query.to_string()));
+        }
+        let conditions = query.split('&')
+            .map(str::parse)
+            .collect::<Result<Vec<Condition>, QueryError>>()?;
+        Ok(Query { conditions })
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub name: String, // Always lowercase
+    pub comparison: Comparison,
+    pub count: usize,
+}
+
+impl Condition {
+    pub fn matches(&self, layout: &Layout) -> bool {
+        self.comparison.compare(count_matching(layout, &layout.spawn_objects(), &self.name), self.count)
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.name, self.comparison, self.count)
+    }
+}
+
+static CONDITION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*([\w\-\.]+)\s*(<=|>=|!=|=|<|>)\s*(\d+)\s*$").unwrap());
+
+impl FromStr for Condition {
+    type Err = QueryError;
+    fn from_str(condition: &str) -> Result<Condition, QueryError> {
+        let captures = CONDITION_RE.captures(condition).ok_or_else(|| QueryError(condition.trim().to_string()))?;
+        Ok(Condition {
+            name: captures[1].to_ascii_lowercase(),
+            comparison: captures[2].parse()?,
+            count: captures[3].parse().map_err(|_| QueryError(condition.trim().to_string()))?,
+        })
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    pub fn compare(&self, actual: usize, expected: usize) -> bool {
+        match self {
+            Comparison::Less => actual < expected,
+            Comparison::LessOrEqual => actual <= expected,
+            Comparison::Equal => actual == expected,
+            Comparison::NotEqual => actual != expected,
+            Comparison::GreaterOrEqual => actual >= expected,
+            Comparison::Greater => actual > expected,
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = QueryError;
+    fn from_str(comparison: &str) -> Result<Comparison, QueryError> {
+        match comparison {
+            "<" => Ok(Comparison::Less),
+            "<=" => Ok(Comparison::LessOrEqual),
+            "=" => Ok(Comparison::Equal),
+            "!=" => Ok(Comparison::NotEqual),
+            ">=" => Ok(Comparison::GreaterOrEqual),
+            ">" => Ok(Comparison::Greater),
+            _ => Err(QueryError(comparison.to_string())),
+        }
+    }
+}
+
+
+/// Returned when a query or one of its conditions can't be parsed. Contains the
+/// offending part of the query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError(pub String);
+
+impl Error for QueryError {}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid search condition '{}'. Conditions look like 'name op count', e.g. 'bey_goma >= 1', and are joined with '&'", self.0)
+    }
+}
+
+
+/// Counts how many things in the layout match the given (lowercase) name.
+fn count_matching(layout: &Layout, spawn_objects: &[(SpawnObject, f32, f32)], name: &str) -> usize {
+    let matching_units = layout.map_units.iter()
+        .filter(|map_unit| map_unit.unit.unit_folder_name.eq_ignore_ascii_case(name))
+        .count();
+    let matching_objects: usize = spawn_objects.iter()
+        .map(|(spawn_object, _, _)| object_names(spawn_object).into_iter().filter(|object_name| object_name.eq_ignore_ascii_case(name)).count())
+        .sum();
+    matching_units + matching_objects
+}
+
+/// All names a spawn object can be searched by. Teki match their own name, their
+/// category, and the name of the treasure they're carrying, if any.
+pub fn object_names(spawn_object: &SpawnObject) -> Vec<&str> {
+    match spawn_object {
+        SpawnObject::Teki(tekiinfo) | SpawnObject::PlantTeki(tekiinfo) => teki_names(&tekiinfo.internal_name, &tekiinfo.carrying),
+        SpawnObject::TekiBunch(teki_list) => teki_list.iter()
+            .flat_map(|(tekiinfo, _)| teki_names(&tekiinfo.internal_name, &tekiinfo.carrying))
+            .collect(),
+        SpawnObject::CapTeki(capinfo, _) => teki_names(&capinfo.internal_name, &capinfo.carrying),
+        SpawnObject::Item(iteminfo) => vec![iteminfo.internal_name.as_str()],
+        SpawnObject::Gate(_) => vec!["gate"],
+        SpawnObject::Hole(_) => vec!["hole"],
+        SpawnObject::Geyser => vec!["geyser"],
+        SpawnObject::Ship => vec!["ship"],
+        SpawnObject::TekiDuplicate => vec![],
+    }
+}
+
+fn teki_names<'a>(internal_name: &'a str, carrying: &'a Option<String>) -> Vec<&'a str> {
+    let mut names = vec![internal_name];
+    // Skipped when it's the same as the teki's name (eggs) so it isn't counted twice.
+    // teki_info.txt is built in and checked by the tests, so a broken one isn't reported here.
+    let category = teki_metadata(internal_name).ok().flatten().and_then(|metadata| metadata.category);
+    names.extend(category.map(|category| category.name()).filter(|category| !category.eq_ignore_ascii_case(internal_name)));
+    names.extend(carrying.as_deref());
+    names
+}
@@ -0,0 +1,222 @@
+/// Exhaustive searches over every possible seed. Covering all 2^32 seeds takes days even
+/// in parallel, so the seed space is split into chunks and progress is saved to a
+/// checkpoint file after each one. Interrupted sweeps pick up from the last completed
+/// chunk when started again with the same checkpoint file.
+
+use std::{error::Error, fmt::{Display, Formatter}, fs::{self, File, OpenOptions}, io::{self, BufRead, BufReader, Write}, path::{Path, PathBuf}};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::caveinfo::FloorInfo;
+use crate::layout::Layout;
+use super::Query;
+
+pub const CHUNK_SIZE: u64 = 1 << 20;
+const NUM_SEEDS: u64 = u32::MAX as u64 + 1;
+/// Matches are written one per line as e.g. "0x1234ABCD\n", so every line is the same length.
+const MATCH_LINE_LEN: u64 = 11;
+
+
+/// Everything needed to resume a sweep. Saved as JSON. Matches can run into the billions
+/// for common queries, so they're appended to a separate file (see `Sweep::matches_path`)
+/// instead of being kept here.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SweepCheckpoint {
+    pub sublevel: String,
+    pub query: String,
+    /// Chunks before this one have all been searched.
+    pub next_chunk: u64,
+    /// How many matches the chunks before `next_chunk` had.
+    pub num_matches: u64,
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: u64,
+}
+
+fn default_chunk_size() -> u64 {
+    CHUNK_SIZE
+}
+
+impl SweepCheckpoint {
+    pub fn num_chunks(&self) -> u64 {
+        NUM_SEEDS / self.chunk_size
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_chunk >= self.num_chunks()
+    }
+
+    /// Fraction of the seed space searched so far, in [0, 1].
+    pub fn progress(&self) -> f32 {
+        self.next_chunk as f32 / self.num_chunks() as f32
+    }
+}
+
+
+pub struct Sweep<'a> {
+    caveinfo: &'a FloorInfo,
+    query: &'a Query,
+    checkpoint_path: PathBuf,
+    pub checkpoint: SweepCheckpoint,
+}
+
+impl<'a> Sweep<'a> {
+    /// Starts a new sweep, or resumes the one saved at `checkpoint_path` if it exists.
+    /// Resuming fails if the saved sweep was for a different sublevel or query.
+    pub fn new_or_resume(caveinfo: &'a FloorInfo, query: &'a Query, checkpoint_path: impl AsRef<Path>) -> Result<Sweep<'a>, SweepError> {
+        Sweep::new_or_resume_with_chunk_size(caveinfo, query, checkpoint_path, CHUNK_SIZE)
+    }
+
+    /// Same as `new_or_resume`, but with `chunk_size` seeds per chunk instead of
+    /// `CHUNK_SIZE`. It has to be a power of two, and the same as the saved sweep's
+    /// when resuming. Small chunks are mostly useful for testing.
+    pub fn new_or_resume_with_chunk_size(caveinfo: &'a FloorInfo, query: &'a Query, checkpoint_path: impl AsRef<Path>, chunk_size: u64) -> Result<Sweep<'a>, SweepError> {
+        if !chunk_size.is_power_of_two() || chunk_size > NUM_SEEDS {
+            return Err(SweepError::InvalidChunkSize(chunk_size));
+        }
+        let checkpoint_path = checkpoint_path.as_ref().to_path_buf();
+        let sublevel = caveinfo.name();
+        let query_str = query.to_string();
+
+        let checkpoint = match fs::read_to_string(&checkpoint_path) {
+            Ok(text) => {
+                let checkpoint: SweepCheckpoint = serde_json::from_str(&text)
+                    .map_err(|e| SweepError::InvalidCheckpoint(checkpoint_path.clone(), e.to_string()))?;
+                if checkpoint.sublevel != sublevel || checkpoint.query != query_str || checkpoint.chunk_size != chunk_size {
+                    return Err(SweepError::CheckpointMismatch {
+                        path: checkpoint_path,
+                        sublevel: checkpoint.sublevel,
+                        query: checkpoint.query,
+                    });
+                }
+                checkpoint
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => SweepCheckpoint {
+                sublevel,
+                query: query_str,
+                next_chunk: 0,
+                num_matches: 0,
+                chunk_size,
+            },
+            Err(e) => return Err(SweepError::Io(e)),
+        };
+
+        let sweep = Sweep { caveinfo, query, checkpoint_path, checkpoint };
+        sweep.discard_unsaved_matches()?;
+        Ok(sweep)
+    }
+
+    /// Where matches are saved, next to the checkpoint, e.g. "sweep_SCx7.matches.txt" for
+    /// "sweep_SCx7.json".
+    pub fn matches_path(&self) -> PathBuf {
+        self.checkpoint_path.with_extension("matches.txt")
+    }
+
+    /// Calls `f` with every match found so far, in order, without loading them all at once.
+    pub fn for_each_match(&self, mut f: impl FnMut(u32)) -> Result<(), SweepError> {
+        if self.checkpoint.num_matches == 0 {
+            return Ok(());
+        }
+        let matches_path = self.matches_path();
+        for line in BufReader::new(File::open(&matches_path)?).lines() {
+            let line = line?;
+            let seed = u32::from_str_radix(line.trim_start_matches("0x"), 16)
+                .map_err(|_| SweepError::InvalidCheckpoint(matches_path.clone(), format!("'{}' isn't a seed", line)))?;
+            f(seed);
+        }
+        Ok(())
+    }
+
+    /// Searches chunks in order until the whole seed space is covered or `max_chunks`
+    /// chunks have been searched in this call, saving matches and the checkpoint after
+    /// each one. `on_chunk` is called after every chunk with the updated checkpoint.
+    pub fn run(&mut self, max_chunks: Option<u64>, mut on_chunk: impl FnMut(&SweepCheckpoint)) -> Result<(), SweepError> {
+        let mut chunks_searched = 0;
+        while !self.checkpoint.is_finished() && max_chunks.map_or(true, |max| chunks_searched < max) {
+            let start = self.checkpoint.next_chunk * self.checkpoint.chunk_size;
+            let mut matches: Vec<u32> = (start..start + self.checkpoint.chunk_size).into_par_iter()
+                .map(|seed| seed as u32)
+                .filter(|seed| self.query.matches(&Layout::generate(*seed, self.caveinfo)))
+                .collect();
+            matches.sort_unstable();
+
+            self.append_matches(&matches)?;
+            self.checkpoint.num_matches += matches.len() as u64;
+            self.checkpoint.next_chunk += 1;
+            self.save()?;
+            chunks_searched += 1;
+            on_chunk(&self.checkpoint);
+        }
+        Ok(())
+    }
+
+    fn append_matches(&self, matches: &[u32]) -> Result<(), SweepError> {
+        if let Some(parent) = self.checkpoint_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(self.matches_path())?;
+        let lines: String = matches.iter().map(|seed| format!("{:#010X}\n", seed)).collect();
+        file.write_all(lines.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Matches are appended before the checkpoint is saved, so if a sweep is interrupted
+    /// in between, the matches file has some from a chunk that will be searched again.
+    /// Those are cut off so they aren't listed twice.
+    fn discard_unsaved_matches(&self) -> Result<(), SweepError> {
+        let matches_path = self.matches_path();
+        let saved_len = self.checkpoint.num_matches * MATCH_LINE_LEN;
+        let file = match OpenOptions::new().write(true).open(&matches_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && saved_len == 0 => return Ok(()),
+            Err(e) => return Err(SweepError::Io(e)),
+        };
+        if file.metadata()?.len() < saved_len {
+            return Err(SweepError::InvalidCheckpoint(matches_path, format!("expected {} matches", self.checkpoint.num_matches)));
+        }
+        file.set_len(saved_len)?;
+        Ok(())
+    }
+
+    /// Writes to a temporary file first and then renames it over the checkpoint, so an
+    /// interruption partway through saving can't corrupt existing progress.
+    fn save(&self) -> Result<(), SweepError> {
+        let temp_path = self.checkpoint_path.with_extension("tmp");
+        let json = serde_json::to_string_pretty(&self.checkpoint)
+            .map_err(|e| SweepError::InvalidCheckpoint(self.checkpoint_path.clone(), e.to_string()))?;
+        fs::write(&temp_path, json)?;
+        fs::rename(&temp_path, &self.checkpoint_path)?;
+        Ok(())
+    }
+}
+
+
+#[derive(Debug)]
+pub enum SweepError {
+    Io(io::Error),
+    InvalidCheckpoint(PathBuf, String),
+    CheckpointMismatch { path: PathBuf, sublevel: String, query: String },
+    InvalidChunkSize(u64),
+}
+
+impl Error for SweepError {}
+
+impl Display for SweepError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SweepError::Io(e) => write!(f, "{}", e),
+            SweepError::InvalidCheckpoint(path, e) => write!(f, "Couldn't read checkpoint file '{}': {}", path.display(), e),
+            SweepError::CheckpointMismatch { path, sublevel, query } => write!(
+                f, "Checkpoint file '{}' is for a different sweep ({}, '{}'). Use a different checkpoint file for this one",
+                path.display(), sublevel, query
+            ),
+            SweepError::InvalidChunkSize(chunk_size) => write!(f, "Sweep chunk size {} isn't a power of two up to 2^32", chunk_size),
+        }
+    }
+}
+
+impl From<io::Error> for SweepError {
+    fn from(e: io::Error) -> SweepError {
+        SweepError::Io(e)
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use super::*;
+use super::sweep::{Sweep, SweepError};
+use crate::caveinfo::{EC1, EC2};
+use crate::layout::Layout;
+
+#[test]
+fn test_parse_query() {
+    let query: Query = "bey_goma >= 1 & Gate=0".parse().unwrap();
+    assert_eq!(query.conditions, vec![
+        Condition { name: "bey_goma".to_string(), comparison: Comparison::GreaterOrEqual, count: 1 },
+        Condition { name: "gate".to_string(), comparison: Comparison::Equal, count: 0 },
+    ]);
+}
+
+#[test]
+fn test_query_round_trip() {
+    let query: Query = "bey_goma>=1&Gate = 0 & hole!=1".parse().unwrap();
+    assert_eq!(query.to_string(), "bey_goma >= 1 & gate = 0 & hole != 1");
+    assert_eq!(query.to_string().parse::<Query>().unwrap(), query);
+}
+
+#[test]
+fn test_parse_invalid_query() {
+    assert_eq!("".parse::<Query>(), Err(QueryError("".to_string())));
+    assert_eq!("bey_goma >= 1 & gate".parse::<Query>(), Err(QueryError("gate".to_string())));
+    assert_eq!("bey_goma => 1".parse::<Query>(), Err(QueryError("bey_goma => 1".to_string())));
+}
+
+/// Sweeps the first 16 seeds of EC1, 4 at a time, stopping and resuming halfway through.
+#[test]
+fn test_sweep_resume() {
+    let floor = EC1.as_ref().unwrap();
+    let query: Query = format!("{} >= 1", floor.teki_info[0].internal_name).parse().unwrap();
+    let expected: Vec<u32> = (0..16).filter(|seed| query.matches(&Layout::generate(*seed, floor))).collect();
+
+    let dir = std::env::temp_dir().join("caveripper_test_sweep_resume");
+    let _ = fs::remove_dir_all(&dir);
+    let checkpoint_path = dir.join("sweep.json");
+
+    let mut sweep = Sweep::new_or_resume_with_chunk_size(floor, &query, &checkpoint_path, 4).unwrap();
+    sweep.run(Some(2), |_| {}).unwrap();
+    assert_eq!(sweep.checkpoint.next_chunk, 2);
+    let saved = sweep.checkpoint.clone();
+
+    // A match saved without its checkpoint, as if the sweep was stopped partway through
+    // saving, is dropped when resuming since its chunk gets searched again.
+    let mut matches_file = OpenOptions::new().append(true).create(true).open(sweep.matches_path()).unwrap();
+    matches_file.write_all(b"0xFFFFFFFF\n").unwrap();
+
+    let mut resumed = Sweep::new_or_resume_with_chunk_size(floor, &query, &checkpoint_path, 4).unwrap();
+    assert_eq!(resumed.checkpoint, saved);
+    resumed.run(Some(2), |_| {}).unwrap();
+    let mut found = Vec::new();
+    resumed.for_each_match(|seed| found.push(seed)).unwrap();
+    assert_eq!(found, expected);
+    assert_eq!(resumed.checkpoint.num_matches, expected.len() as u64);
+    assert_eq!(resumed.checkpoint.next_chunk, 4);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_sweep_checkpoint_mismatch() {
+    let query: Query = "kochappy >= 2".parse().unwrap();
+    let other_query: Query = "gate = 0".parse().unwrap();
+    let dir = std::env::temp_dir().join("caveripper_test_sweep_checkpoint_mismatch");
+    let _ = fs::remove_dir_all(&dir);
+    let checkpoint_path = dir.join("sweep.json");
+
+    let ec1 = EC1.as_ref().unwrap();
+    Sweep::new_or_resume_with_chunk_size(ec1, &query, &checkpoint_path, 4).unwrap().run(Some(1), |_| {}).unwrap();
+    for result in [
+        Sweep::new_or_resume_with_chunk_size(ec1, &other_query, &checkpoint_path, 4),
+        Sweep::new_or_resume_with_chunk_size(EC2.as_ref().unwrap(), &query, &checkpoint_path, 4),
+        Sweep::new_or_resume(ec1, &query, &checkpoint_path),
+    ] {
+        assert!(matches!(result, Err(SweepError::CheckpointMismatch { .. })));
+    }
+    assert!(matches!(Sweep::new_or_resume_with_chunk_size(ec1, &query, dir.join("other.json"), 3), Err(SweepError::InvalidChunkSize(3))));
+
+    fs::remove_dir_all(dir).unwrap();
+}
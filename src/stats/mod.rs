@@ -0,0 +1,149 @@
+/// Distributions of what spawns on a sublevel, collected by generating many layouts.
+/// Useful for answering questions like "how often does SR7 have no Bloysters?"
+/// without having to search for specific seeds.
+
+#[cfg(test)]
+mod test;
+
+use std::collections::BTreeMap;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::caveinfo::FloorInfo;
+use crate::layout::{Layout, SpawnObject};
+
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SublevelStats {
+    pub num_layouts: usize,
+    /// Distribution of the number of each teki per layout, by internal name. Every teki
+    /// that can spawn on the sublevel is included, even if it never actually spawned.
+    pub teki: BTreeMap<String, Distribution>,
+    /// How many times each treasure spawned in each map unit. Treasures carried by teki
+    /// are counted in the unit the teki spawned in.
+    pub treasure_units: BTreeMap<String, BTreeMap<String, usize>>,
+    /// How many times the exit hole or geyser spawned in each map unit.
+    pub exit_units: BTreeMap<String, usize>,
+    pub gates: Distribution,
+    pub map_units: Distribution,
+}
+
+impl SublevelStats {
+    /// Generates `num` layouts from random seeds in parallel and collects stats from them.
+    pub fn sample(caveinfo: &FloorInfo, num: usize) -> SublevelStats {
+        (0..num).into_par_iter()
+            .fold(SublevelStats::default, |mut stats, _| {
+                stats.add_layout(&Layout::generate(rand::random(), caveinfo), caveinfo);
+                stats
+            })
+            .reduce(SublevelStats::default, |mut a, b| {
+                a.merge(b);
+                a
+            })
+    }
+
+    pub fn add_layout(&mut self, layout: &Layout, caveinfo: &FloorInfo) {
+        let mut teki_counts: BTreeMap<String, usize> = caveinfo.teki_info.iter().map(|teki| teki.internal_name.clone())
+            .chain(caveinfo.cap_info.iter().map(|cap| cap.internal_name.clone()))
+            .map(|name| (name, 0))
+            .collect();
+        let mut num_gates = 0;
+
+        for map_unit in layout.map_units.iter() {
+            let unit_name = &map_unit.unit.unit_folder_name;
+            let spawn_objects = map_unit.spawnpoints.iter()
+                .flat_map(|spawn_point| spawn_point.contains.iter().chain(spawn_point.falling_cap_teki.iter()));
+            for spawn_object in spawn_objects {
+                let mut add_teki = |name: &String, carrying: &Option<String>| {
+                    *teki_counts.entry(name.clone()).or_default() += 1;
+                    if let Some(treasure) = carrying {
+                        *self.treasure_units.entry(treasure.clone()).or_default().entry(unit_name.clone()).or_default() += 1;
+                    }
+                };
+                match spawn_object {
+                    SpawnObject::Teki(tekiinfo) | SpawnObject::PlantTeki(tekiinfo) => add_teki(&tekiinfo.internal_name, &tekiinfo.carrying),
+                    SpawnObject::TekiBunch(teki_list) => teki_list.iter().for_each(|(tekiinfo, _)| add_teki(&tekiinfo.internal_name, &tekiinfo.carrying)),
+                    SpawnObject::CapTeki(capinfo, _) => add_teki(&capinfo.internal_name, &capinfo.carrying),
+                    SpawnObject::Item(iteminfo) => {
+                        *self.treasure_units.entry(iteminfo.internal_name.clone()).or_default().entry(unit_name.clone()).or_default() += 1;
+                    },
+                    SpawnObject::Hole(_) | SpawnObject::Geyser => *self.exit_units.entry(unit_name.clone()).or_default() += 1,
+                    SpawnObject::Gate(_) | SpawnObject::Ship | SpawnObject::TekiDuplicate => {},
+                }
+            }
+
+            // Door seams only ever hold gates and single teki.
+            for door in map_unit.doors.iter() {
+                match door.borrow().seam_spawnpoint.as_ref() {
+                    Some(SpawnObject::Gate(_)) => num_gates += 1,
+                    Some(SpawnObject::Teki(tekiinfo)) => *teki_counts.entry(tekiinfo.internal_name.clone()).or_default() += 1,
+                    _ => {},
+                }
+            }
+        }
+
+        for (name, count) in teki_counts {
+            self.teki.entry(name).or_default().add(count);
+        }
+        self.gates.add(num_gates);
+        self.map_units.add(layout.map_units.len());
+        self.num_layouts += 1;
+    }
+
+    pub fn merge(&mut self, other: SublevelStats) {
+        self.num_layouts += other.num_layouts;
+        for (name, distribution) in other.teki {
+            self.teki.entry(name).or_default().merge(&distribution);
+        }
+        for (treasure, units) in other.treasure_units {
+            let own_units = self.treasure_units.entry(treasure).or_default();
+            for (unit, count) in units {
+                *own_units.entry(unit).or_default() += count;
+            }
+        }
+        for (unit, count) in other.exit_units {
+            *self.exit_units.entry(unit).or_default() += count;
+        }
+        self.gates.merge(&other.gates);
+        self.map_units.merge(&other.map_units);
+    }
+}
+
+
+/// A histogram of how many layouts had each count of something.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Distribution(pub BTreeMap<usize, usize>);
+
+impl Distribution {
+    pub fn add(&mut self, count: usize) {
+        *self.0.entry(count).or_default() += 1;
+    }
+
+    pub fn merge(&mut self, other: &Distribution) {
+        for (count, occurrences) in other.0.iter() {
+            *self.0.entry(*count).or_default() += occurrences;
+        }
+    }
+
+    /// Number of layouts recorded.
+    pub fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+
+    pub fn mean(&self) -> f32 {
+        self.0.iter().map(|(count, occurrences)| count * occurrences).sum::<usize>() as f32 / self.total().max(1) as f32
+    }
+
+    pub fn min(&self) -> usize {
+        self.0.keys().next().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> usize {
+        self.0.keys().next_back().copied().unwrap_or_default()
+    }
+
+    /// The fraction of layouts with exactly this count.
+    pub fn probability(&self, count: usize) -> f32 {
+        self.0.get(&count).copied().unwrap_or_default() as f32 / self.total().max(1) as f32
+    }
+}
@@ -0,0 +1,80 @@
+use itertools::Itertools;
+
+use super::*;
+use crate::caveinfo::EC1;
+
+#[test]
+fn test_distribution() {
+    let mut distribution = Distribution::default();
+    [0, 2, 2, 3].into_iter().for_each(|count| distribution.add(count));
+    let mut other = Distribution::default();
+    other.add(3);
+    distribution.merge(&other);
+
+    assert_eq!(distribution.total(), 5);
+    assert_eq!(distribution.mean(), 2.0);
+    assert_eq!((distribution.min(), distribution.max()), (0, 3));
+    assert_eq!(distribution.probability(0), 0.2);
+    assert_eq!(distribution.probability(1), 0.0);
+}
+
+#[test]
+fn test_add_layout() {
+    let floor = EC1.as_ref().unwrap();
+    let seeds = [0x1234ABCD, 0x00000001];
+    let layouts = seeds.map(|seed| Layout::generate(seed, floor));
+    let mut stats = SublevelStats::default();
+    layouts.iter().for_each(|layout| stats.add_layout(layout, floor));
+
+    assert_eq!(stats.num_layouts, 2);
+    assert_eq!(stats.map_units, Distribution(layouts.iter().map(|layout| layout.map_units.len()).counts().into_iter().collect()));
+    for teki in floor.teki_info.iter() {
+        let expected: Vec<usize> = layouts.iter()
+            .map(|layout| layout.spawn_objects().iter()
+                .filter(|(spawn_object, _, _)| match spawn_object {
+                    SpawnObject::Teki(t) | SpawnObject::PlantTeki(t) => t.internal_name == teki.internal_name,
+                    SpawnObject::CapTeki(cap, _) => cap.internal_name == teki.internal_name,
+                    _ => false,
+                })
+                .count())
+            .collect();
+        assert_eq!(stats.teki[&teki.internal_name], Distribution(expected.into_iter().counts().into_iter().collect()), "{}", teki.internal_name);
+    }
+
+    let count_all = |predicate: fn(&SpawnObject) -> bool| layouts.iter()
+        .flat_map(|layout| layout.spawn_objects())
+        .filter(|(spawn_object, _, _)| predicate(spawn_object))
+        .count();
+    assert_eq!(stats.gates.0.iter().map(|(gates, layouts)| gates * layouts).sum::<usize>(), count_all(|o| matches!(o, SpawnObject::Gate(_))));
+    assert_eq!(stats.exit_units.values().sum::<usize>(), count_all(|o| matches!(o, SpawnObject::Hole(_) | SpawnObject::Geyser)));
+    let treasures = stats.treasure_units.values().flat_map(|units| units.values()).sum::<usize>();
+    assert_eq!(treasures, count_all(|o| match o {
+        SpawnObject::Item(_) => true,
+        SpawnObject::Teki(teki) => teki.carrying.is_some(),
+        SpawnObject::CapTeki(cap, _) => cap.carrying.is_some(),
+        _ => false,
+    }));
+
+    // Adding the layouts to separate stats and merging them gives the same result.
+    let mut merged = SublevelStats::default();
+    merged.add_layout(&layouts[0], floor);
+    let mut second = SublevelStats::default();
+    second.add_layout(&layouts[1], floor);
+    merged.merge(second);
+    assert_eq!((merged.num_layouts, &merged.teki, &merged.treasure_units, &merged.exit_units), (2, &stats.teki, &stats.treasure_units, &stats.exit_units));
+    assert_eq!((&merged.gates, &merged.map_units), (&stats.gates, &stats.map_units));
+}
+
+#[test]
+fn test_sample() {
+    let floor = EC1.as_ref().unwrap();
+    let stats = SublevelStats::sample(floor, 20);
+    assert_eq!(stats.num_layouts, 20);
+    assert_eq!((stats.map_units.total(), stats.gates.total()), (20, 20));
+    // Every teki that can spawn gets a count for every layout, even when it's zero.
+    assert_eq!(stats.teki.len(), floor.teki_info.iter().map(|teki| &teki.internal_name).chain(floor.cap_info.iter().map(|cap| &cap.internal_name)).unique().count());
+    assert!(stats.teki.values().all(|distribution| distribution.total() == 20));
+    // EC1 has an exit hole and no geyser, so each layout has exactly one way out.
+    assert!(!floor.has_geyser);
+    assert_eq!(stats.exit_units.values().sum::<usize>(), 20);
+}